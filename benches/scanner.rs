@@ -0,0 +1,293 @@
+//! Scanner throughput on a large generated script, compared with the
+//! char-vector scanner it replaced.
+//!
+//! Run with `cargo bench --bench scanner`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use rlox::scanner::Scanner;
+use rlox::token::{Span, Token, TokenType};
+
+/// Builds a script of roughly `size` bytes mixing every kind of token.
+fn generate_source(size: usize) -> String {
+    let chunk = "\
+// counts things
+class Counter < Base {
+    init(start) { this.count = start; }
+    increment() { this.count = this.count + 1.5; return this; }
+}
+/* a block comment
+   spanning lines */
+fun fib(n) { if (n <= 1) return n; return fib(n - 2) + fib(n - 1); }
+var message = \"hello, world\";
+for (var i = 0; i < 100; i = i + 1) { print message != nil and i >= 10 or !false; }
+";
+    chunk.repeat(size / chunk.len() + 1)
+}
+
+/// The scanning loop as it was before lexemes borrowed from the source:
+/// the text is collected into a `Vec<char>` up front and every lexeme and
+/// literal is rebuilt with `String::from_iter` over a char slice. Only the
+/// error handling is dropped, since the generated script is valid.
+mod baseline {
+    use super::*;
+
+    pub struct CharScanner {
+        source: Vec<char>,
+        tokens: Vec<Token<'static>>,
+        start: usize,
+        current: usize,
+        line: usize,
+        byte_start: usize,
+        byte_current: usize,
+        line_start: usize,
+        start_line: usize,
+        start_column: usize,
+    }
+
+    impl CharScanner {
+        pub fn new(source: &str) -> CharScanner {
+            CharScanner {
+                source: source.chars().collect(),
+                tokens: Vec::new(),
+                start: 0,
+                current: 0,
+                line: 1,
+                byte_start: 0,
+                byte_current: 0,
+                line_start: 0,
+                start_line: 1,
+                start_column: 1,
+            }
+        }
+
+        pub fn scan_tokens(mut self) -> Vec<Token<'static>> {
+            while !self.is_at_end() {
+                self.start = self.current;
+                self.byte_start = self.byte_current;
+                self.start_line = self.line;
+                self.start_column = self.current - self.line_start + 1;
+                self.scan_token();
+            }
+            let mut eof = Token::eof(self.line);
+            eof.span = Span::new(self.byte_current, self.byte_current);
+            self.tokens.push(eof);
+            self.tokens
+        }
+
+        fn is_at_end(&self) -> bool {
+            self.current >= self.source.len()
+        }
+
+        fn scan_token(&mut self) {
+            let c = self.advance();
+            match c {
+                '(' => self.add_token(TokenType::LeftParen),
+                ')' => self.add_token(TokenType::RightParen),
+                '{' => self.add_token(TokenType::LeftBrace),
+                '}' => self.add_token(TokenType::RightBrace),
+                ',' => self.add_token(TokenType::Comma),
+                '.' => self.add_token(TokenType::Dot),
+                '-' => self.add_token(TokenType::Minus),
+                '+' => self.add_token(TokenType::Plus),
+                ';' => self.add_token(TokenType::Semicolon),
+                '*' => self.add_token(TokenType::Star),
+                '!' => self.add_pair('=', TokenType::BangEqual, TokenType::Bang),
+                '=' => self.add_pair('=', TokenType::EqualEqual, TokenType::Equal),
+                '<' => self.add_pair('=', TokenType::LessEqual, TokenType::Less),
+                '>' => self.add_pair('=', TokenType::GreaterEqual, TokenType::Greater),
+                '/' => {
+                    if self.is_match('/') {
+                        while self.peek().is_some_and(|ch| ch != '\n') {
+                            self.advance();
+                        }
+                    } else if self.is_match('*') {
+                        self.scan_comment();
+                    } else {
+                        self.add_token(TokenType::Slash)
+                    }
+                }
+                ' ' | '\r' | '\t' | '\n' => {}
+                '"' => self.string(),
+                ch if ch.is_ascii_digit() => self.number(),
+                ch if ch.is_ascii_alphabetic() || ch == '_' => self.identifier(),
+                ch => panic!("Unexpected character: {}", ch),
+            }
+        }
+
+        fn scan_comment(&mut self) {
+            loop {
+                match self.peek() {
+                    Some('*') => {
+                        self.advance();
+                        if self.is_match('/') {
+                            if self.is_match('*') {
+                                self.scan_comment();
+                            } else {
+                                return;
+                            }
+                        }
+                    }
+                    Some('/') => {
+                        self.advance();
+                        if self.is_match('*') {
+                            self.scan_comment();
+                        }
+                    }
+                    Some(_) => {
+                        self.advance();
+                    }
+                    None => panic!("Unterminated comments"),
+                }
+            }
+        }
+
+        fn identifier(&mut self) {
+            while self
+                .peek()
+                .is_some_and(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+            {
+                self.advance();
+            }
+            let text = String::from_iter(self.source[self.start..self.current].iter());
+            let token_type = match text.as_str() {
+                "and" => TokenType::And,
+                "class" => TokenType::Class,
+                "else" => TokenType::Else,
+                "false" => TokenType::False,
+                "true" => TokenType::True,
+                "for" => TokenType::For,
+                "if" => TokenType::If,
+                "fun" => TokenType::Fun,
+                "nil" => TokenType::Nil,
+                "or" => TokenType::Or,
+                "print" => TokenType::Print,
+                "return" => TokenType::Return,
+                "super" => TokenType::Super,
+                "this" => TokenType::This,
+                "var" => TokenType::Var,
+                "while" => TokenType::While,
+                _ => TokenType::Identifier,
+            };
+            self.add_token(token_type);
+        }
+
+        fn string(&mut self) {
+            while self.peek().is_some_and(|ch| ch != '"') {
+                self.advance();
+            }
+            assert!(!self.is_at_end(), "Unterminated string.");
+            // The closing '"'.
+            self.advance();
+            let value = String::from_iter(self.source[self.start + 1..self.current - 1].iter());
+            black_box(value);
+            self.add_token(TokenType::String);
+        }
+
+        fn number(&mut self) {
+            while self.peek().is_some_and(|ch| ch.is_ascii_digit()) {
+                self.advance();
+            }
+            if self.peek() == Some('.') && self.peek_next().is_some_and(|ch| ch.is_ascii_digit()) {
+                self.advance();
+                while self.peek().is_some_and(|ch| ch.is_ascii_digit()) {
+                    self.advance();
+                }
+            }
+            let text = String::from_iter(self.source[self.start..self.current].iter());
+            black_box(text.parse::<f64>().unwrap());
+            self.add_token(TokenType::Number);
+        }
+
+        fn peek(&self) -> Option<char> {
+            self.source.get(self.current).copied()
+        }
+
+        fn peek_next(&self) -> Option<char> {
+            self.source.get(self.current + 1).copied()
+        }
+
+        fn is_match(&mut self, expected: char) -> bool {
+            if self.peek() == Some(expected) {
+                self.advance();
+                true
+            } else {
+                false
+            }
+        }
+
+        fn add_pair(&mut self, expected: char, matched: TokenType, single: TokenType) {
+            let token_type = if self.is_match(expected) {
+                matched
+            } else {
+                single
+            };
+            self.add_token(token_type)
+        }
+
+        fn add_token(&mut self, token_type: TokenType) {
+            let lexeme = String::from_iter(self.source[self.start..self.current].iter());
+            let mut token = Token::new(token_type, lexeme, None, self.start_line);
+            token.column = self.start_column;
+            token.span = Span::new(self.byte_start, self.byte_current);
+            self.tokens.push(token);
+        }
+
+        fn advance(&mut self) -> char {
+            let ch = self.source[self.current];
+            self.current += 1;
+            self.byte_current += ch.len_utf8();
+            if ch == '\n' {
+                self.line += 1;
+                self.line_start = self.current;
+            }
+            ch
+        }
+    }
+}
+
+/// Runs `scan` over `source` `iterations` times and returns the token
+/// count and the average time per scan.
+fn measure(iterations: u32, mut scan: impl FnMut() -> usize) -> (usize, Duration) {
+    let mut tokens = 0;
+    let mut elapsed = Duration::ZERO;
+    for _ in 0..iterations {
+        let started = Instant::now();
+        tokens = scan();
+        elapsed += started.elapsed();
+    }
+    (tokens, elapsed / iterations)
+}
+
+fn main() {
+    let source = generate_source(8 * 1024 * 1024);
+    let iterations = 10;
+    let megabytes = source.len() as f64 / (1024.0 * 1024.0);
+    let report = |name: &str, (tokens, per_iteration): (usize, Duration)| {
+        println!(
+            "{}: scanned {:.1} MiB ({} tokens) in {:?} per iteration: {:.1} MiB/s",
+            name,
+            megabytes,
+            tokens,
+            per_iteration,
+            megabytes / per_iteration.as_secs_f64()
+        );
+    };
+
+    report(
+        "char vector (before)",
+        measure(iterations, || {
+            let tokens = baseline::CharScanner::new(black_box(&source)).scan_tokens();
+            black_box(&tokens).len()
+        }),
+    );
+    report(
+        "byte offsets (after)",
+        measure(iterations, || {
+            let (tokens, errors) = Scanner::new(black_box(&source)).scan_tokens();
+            assert!(errors.is_empty());
+            black_box(&tokens).len()
+        }),
+    );
+}
@@ -13,11 +13,12 @@ pub fn define_ast(input: TokenStream) -> TokenStream {
     let expr_ast = parse_macro_input!(input as ExprAst);
     let base_ident = &expr_ast.ident;
     let structs = &expr_ast.structs;
+    let param_ident = format_ident!("{}", base_ident.to_string().to_lowercase());
 
     // define `Visitor` trait
     let visit_method_names = structs
         .iter()
-        .map(|s| (format_ident!("visit_{}", s.get_ident_name_lowercase())))
+        .map(|s| format_ident!("visit_{}", s.get_ident_name_lowercase()))
         .collect::<Vec<_>>();
     let visit_idents = structs.iter().map(|s| s.ident.clone()).collect::<Vec<_>>();
 
@@ -25,61 +26,59 @@ pub fn define_ast(input: TokenStream) -> TokenStream {
         pub trait Visitor {
             type Res;
             #(
-                fn #visit_method_names(&self, expr: &#visit_idents) -> Result<Self::Res, LoxError>;
+                fn #visit_method_names(&mut self, #param_ident: &#visit_idents) -> Result<Self::Res, LoxError>;
             )*
         }
     });
 
-    // define `Expr` trait
+    // define the base enum, with one variant per struct.
     output.extend(quote! {
-        pub trait #base_ident {
-            fn accept(&self, visitor: Box<&dyn Visitor>) -> Result<Visitor::Res, LoxError>;
+        #[derive(Debug, Clone)]
+        pub enum #base_ident {
+            #(
+                #visit_idents(#visit_idents),
+            )*
+        }
+
+        impl #base_ident {
+            pub fn accept<V: Visitor + ?Sized>(&self, visitor: &mut V) -> Result<V::Res, LoxError> {
+                match self {
+                    #(
+                        #base_ident::#visit_idents(#param_ident) => visitor.#visit_method_names(#param_ident),
+                    )*
+                }
+            }
         }
     });
 
     // define structs.
     structs.iter().for_each(|s| {
         let ident = &s.ident;
-        let visit_method_name = format_ident!("visit_{}", s.get_ident_name_lowercase());
-        let trait_field_names = s
-            .fields
-            .iter()
-            .filter(|f| f.is_trait)
-            .map(|f| f.name.clone())
-            .collect::<Vec<_>>();
-        let trait_field_tys = s
-            .fields
-            .iter()
-            .filter(|f| f.is_trait)
-            .map(|f| f.ty.clone())
-            .collect::<Vec<_>>();
-
-        let field_names = s
-            .fields
-            .iter()
-            .filter(|f| !f.is_trait)
-            .map(|f| f.name.clone())
-            .collect::<Vec<_>>();
+        let field_names = s.fields.iter().map(|f| f.name.clone()).collect::<Vec<_>>();
         let field_tys = s
             .fields
             .iter()
-            .filter(|f| !f.is_trait)
-            .map(|f| f.ty.clone())
+            .map(|f| {
+                let ty = &f.ty;
+                if f.is_trait {
+                    quote!(Box<#ty>)
+                } else {
+                    quote!(#ty)
+                }
+            })
             .collect::<Vec<_>>();
 
         output.extend(quote! {
+                #[derive(Debug, Clone)]
                 pub struct #ident {
-                    #(
-                        pub #trait_field_names: Box<dyn #trait_field_tys>,
-                    )*
                     #(
                         pub #field_names: #field_tys,
                     )*
                 }
 
-                impl #base_ident for #ident {
-                    fn accept(&self, visitor: Box<&dyn Visitor>) -> Result<Visitor::Res, LoxError> {
-                        visitor.#visit_method_name(self)
+                impl From<#ident> for #base_ident {
+                    fn from(#param_ident: #ident) -> Self {
+                        #base_ident::#ident(#param_ident)
                     }
                 }
         });
@@ -0,0 +1,162 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::class::LoxInstance;
+use crate::environment::Environment;
+use crate::error::LoxError;
+use crate::interpreter::Interpreter;
+use crate::literal::Object;
+use crate::stmt;
+
+/// Anything that can be called with `()` from Lox code.
+pub trait LoxCallable: fmt::Debug + fmt::Display {
+    fn arity(&self) -> usize;
+
+    /// The name shown in stack traces: `f`, `Class.method` or `clock`.
+    fn name(&self) -> String;
+
+    fn call(
+        self: Rc<Self>,
+        interpreter: &mut Interpreter,
+        arguments: Vec<Object>,
+    ) -> Result<Object, LoxError>;
+}
+
+/// A user-defined function together with the environment it closes over.
+#[derive(Debug)]
+pub struct LoxFunction {
+    declaration: stmt::Function,
+    closure: Rc<RefCell<Environment>>,
+    is_initializer: bool,
+    /// The class this is a method of.
+    class: Option<Rc<str>>,
+}
+
+impl LoxFunction {
+    pub fn new(
+        declaration: stmt::Function,
+        closure: Rc<RefCell<Environment>>,
+        is_initializer: bool,
+    ) -> Self {
+        LoxFunction {
+            declaration,
+            closure,
+            is_initializer,
+            class: None,
+        }
+    }
+
+    /// Makes this a method of `class`.
+    pub fn in_class(self, class: Rc<str>) -> LoxFunction {
+        LoxFunction {
+            class: Some(class),
+            ..self
+        }
+    }
+
+    /// Returns a copy of this method whose closure binds `this` to
+    /// `instance`.
+    pub fn bind(&self, instance: Rc<RefCell<LoxInstance>>) -> LoxFunction {
+        let mut environment = Environment::new(Rc::clone(&self.closure));
+        environment.define("this", Object::Instance(instance));
+        LoxFunction {
+            declaration: self.declaration.clone(),
+            closure: Rc::new(RefCell::new(environment)),
+            is_initializer: self.is_initializer,
+            class: self.class.clone(),
+        }
+    }
+}
+
+impl LoxCallable for LoxFunction {
+    fn arity(&self) -> usize {
+        self.declaration.params.len()
+    }
+
+    fn name(&self) -> String {
+        match &self.class {
+            Some(class) => format!("{}.{}", class, self.declaration.name.lexeme),
+            None => self.declaration.name.lexeme.to_string(),
+        }
+    }
+
+    fn call(
+        self: Rc<Self>,
+        interpreter: &mut Interpreter,
+        arguments: Vec<Object>,
+    ) -> Result<Object, LoxError> {
+        let mut environment = Environment::new(Rc::clone(&self.closure));
+        for (param, argument) in self.declaration.params.iter().zip(arguments) {
+            environment.define(&param.lexeme, argument);
+        }
+
+        let value = interpreter.execute_block(&self.declaration.body, environment)?;
+        if self.is_initializer {
+            // `init` always hands back the instance, even on a bare `return;`.
+            let this = self.closure.borrow().lookup_at(0, "this");
+            return Ok(this.unwrap_or(Object::Nil));
+        }
+        Ok(value.unwrap_or(Object::Nil))
+    }
+}
+
+impl fmt::Display for LoxFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<fn {}>", self.declaration.name.lexeme)
+    }
+}
+
+/// A function implemented in Rust and exposed to Lox as a global.
+pub struct NativeFunction {
+    pub name: &'static str,
+    arity: usize,
+    function: fn(&[Object]) -> Result<Object, LoxError>,
+}
+
+impl NativeFunction {
+    /// Every native function registered in the global environment.
+    pub fn all() -> Vec<NativeFunction> {
+        vec![NativeFunction {
+            name: "clock",
+            arity: 0,
+            function: |_| {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default();
+                Ok(Object::Num(now.as_secs_f64()))
+            },
+        }]
+    }
+}
+
+impl LoxCallable for NativeFunction {
+    fn arity(&self) -> usize {
+        self.arity
+    }
+
+    fn name(&self) -> String {
+        self.name.to_string()
+    }
+
+    fn call(
+        self: Rc<Self>,
+        _interpreter: &mut Interpreter,
+        arguments: Vec<Object>,
+    ) -> Result<Object, LoxError> {
+        (self.function)(&arguments)
+    }
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NativeFunction({})", self.name)
+    }
+}
+
+impl fmt::Display for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<native fn>")
+    }
+}
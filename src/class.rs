@@ -0,0 +1,144 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::callable::{LoxCallable, LoxFunction};
+use crate::error::{ErrorCode, LoxError};
+use crate::interpreter::Interpreter;
+use crate::literal::Object;
+use crate::suggest;
+use crate::token::Token;
+
+#[derive(Debug)]
+pub struct LoxClass {
+    pub name: String,
+    superclass: Option<Rc<LoxClass>>,
+    methods: HashMap<String, Rc<LoxFunction>>,
+}
+
+impl LoxClass {
+    pub fn new(
+        name: String,
+        superclass: Option<Rc<LoxClass>>,
+        methods: HashMap<String, Rc<LoxFunction>>,
+    ) -> Self {
+        LoxClass {
+            name,
+            superclass,
+            methods,
+        }
+    }
+
+    /// Finds a method on this class or, failing that, up the superclass
+    /// chain.
+    pub fn find_method(&self, name: &str) -> Option<Rc<LoxFunction>> {
+        if let Some(method) = self.methods.get(name) {
+            return Some(Rc::clone(method));
+        }
+        self.superclass
+            .as_ref()
+            .and_then(|superclass| superclass.find_method(name))
+    }
+
+    /// The names of the methods `find_method` can find.
+    pub fn method_names(&self) -> Vec<&str> {
+        let mut names = self.methods.keys().map(String::as_str).collect::<Vec<_>>();
+        if let Some(superclass) = &self.superclass {
+            names.extend(superclass.method_names());
+        }
+        names
+    }
+}
+
+impl LoxClass {
+    pub fn undefined_property(name: &Token, similar: Option<&str>) -> LoxError {
+        let err = LoxError::runtime(
+            ErrorCode::UndefinedProperty,
+            name,
+            format!("Undefined property '{}'.", name.lexeme),
+        );
+        match similar {
+            Some(similar) => err.with_help(format!("Did you mean '{}'?", similar)),
+            None => err,
+        }
+    }
+}
+
+impl LoxCallable for LoxClass {
+    fn arity(&self) -> usize {
+        self.find_method("init")
+            .map_or(0, |initializer| initializer.arity())
+    }
+
+    /// Named after `init`, which is what runs.
+    fn name(&self) -> String {
+        self.find_method("init")
+            .map_or_else(|| self.name.clone(), |initializer| initializer.name())
+    }
+
+    /// Calling a class creates a new instance and runs `init` on it, if the
+    /// class has one.
+    fn call(
+        self: Rc<Self>,
+        interpreter: &mut Interpreter,
+        arguments: Vec<Object>,
+    ) -> Result<Object, LoxError> {
+        let instance = Rc::new(RefCell::new(LoxInstance::new(Rc::clone(&self))));
+        if let Some(initializer) = self.find_method("init") {
+            Rc::new(initializer.bind(Rc::clone(&instance))).call(interpreter, arguments)?;
+        }
+        Ok(Object::Instance(instance))
+    }
+}
+
+impl fmt::Display for LoxClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+#[derive(Debug)]
+pub struct LoxInstance {
+    class: Rc<LoxClass>,
+    fields: HashMap<String, Object>,
+}
+
+impl LoxInstance {
+    pub fn new(class: Rc<LoxClass>) -> Self {
+        LoxInstance {
+            class,
+            fields: HashMap::new(),
+        }
+    }
+
+    /// Looks up a field, falling back to a method bound to `instance`.
+    /// Fields shadow methods.
+    pub fn get(instance: &Rc<RefCell<LoxInstance>>, name: &Token) -> Result<Object, LoxError> {
+        if let Some(value) = instance.borrow().fields.get(name.lexeme.as_ref()) {
+            return Ok(value.clone());
+        }
+
+        let method = instance.borrow().class.find_method(&name.lexeme);
+        match method {
+            Some(method) => Ok(Object::Callable(Rc::new(method.bind(Rc::clone(instance))))),
+            None => {
+                let instance = instance.borrow();
+                let fields = instance.fields.keys().map(String::as_str);
+                let similar =
+                    suggest::closest(&name.lexeme, fields.chain(instance.class.method_names()));
+                Err(LoxClass::undefined_property(name, similar))
+            }
+        }
+    }
+
+    pub fn set(&mut self, name: &Token, value: Object) {
+        self.fields.insert(name.lexeme.to_string(), value);
+    }
+}
+
+impl fmt::Display for LoxInstance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} instance", self.class.name)
+    }
+}
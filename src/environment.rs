@@ -0,0 +1,131 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::error::{ErrorCode, LoxError};
+use crate::literal::Object;
+use crate::token::Token;
+
+#[derive(Debug, Default)]
+pub struct Environment {
+    values: HashMap<String, Object>,
+    enclosing: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Environment {
+    pub fn new(enclosing: Rc<RefCell<Environment>>) -> Self {
+        Environment {
+            values: HashMap::new(),
+            enclosing: Some(enclosing),
+        }
+    }
+
+    /// Binds `name` in this scope. Redefining an existing name simply
+    /// replaces its value, which is what Lox allows for globals.
+    pub fn define(&mut self, name: &str, value: Object) {
+        self.values.insert(name.to_string(), value);
+    }
+
+    pub fn get(&self, name: &Token) -> Result<Object, LoxError> {
+        if let Some(value) = self.values.get(name.lexeme.as_ref()) {
+            return Ok(value.clone());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow().get(name),
+            None => Err(Environment::undefined(name)),
+        }
+    }
+
+    pub fn assign(&mut self, name: &Token, value: Object) -> Result<(), LoxError> {
+        if let Some(slot) = self.values.get_mut(name.lexeme.as_ref()) {
+            *slot = value;
+            return Ok(());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().assign(name, value),
+            None => Err(Environment::undefined(name)),
+        }
+    }
+
+    /// Reads `name` from the scope exactly `distance` hops up the chain, as
+    /// computed by the resolver.
+    pub fn get_at(&self, distance: usize, name: &Token) -> Result<Object, LoxError> {
+        self.lookup_at(distance, &name.lexeme)
+            .ok_or_else(|| Environment::undefined(name))
+    }
+
+    /// Like `get_at`, for the implicit `this` and `super` bindings that have
+    /// no token of their own to report an error against.
+    pub fn lookup_at(&self, distance: usize, name: &str) -> Option<Object> {
+        if distance == 0 {
+            return self.values.get(name).cloned();
+        }
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow().lookup_at(distance - 1, name),
+            None => None,
+        }
+    }
+
+    pub fn assign_at(
+        &mut self,
+        distance: usize,
+        name: &Token,
+        value: Object,
+    ) -> Result<(), LoxError> {
+        if distance == 0 {
+            self.values.insert(name.lexeme.to_string(), value);
+            return Ok(());
+        }
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().assign_at(distance - 1, name, value),
+            None => Err(Environment::undefined(name)),
+        }
+    }
+
+    /// Every name visible from this scope, innermost first.
+    pub fn names(&self) -> Vec<String> {
+        let mut names = self.values.keys().cloned().collect::<Vec<_>>();
+        if let Some(enclosing) = &self.enclosing {
+            names.extend(enclosing.borrow().names());
+        }
+        names
+    }
+
+    fn undefined(name: &Token) -> LoxError {
+        LoxError::runtime(
+            ErrorCode::UndefinedVariable,
+            name,
+            format!("Undefined variable '{}'.", name.lexeme),
+        )
+    }
+}
+
+#[test]
+fn test_environment() {
+    use crate::token::TokenType;
+
+    let name = Token::new(TokenType::Identifier, "a".to_string(), None, 1);
+    let globals = Rc::new(RefCell::new(Environment::default()));
+    globals.borrow_mut().define("a", Object::Num(1.));
+
+    let mut local = Environment::new(Rc::clone(&globals));
+    assert_eq!(local.get(&name).unwrap(), Object::Num(1.));
+
+    local.assign(&name, Object::Num(2.)).unwrap();
+    assert_eq!(globals.borrow().get(&name).unwrap(), Object::Num(2.));
+
+    local.define("a", Object::Nil);
+    assert_eq!(local.get(&name).unwrap(), Object::Nil);
+    assert_eq!(globals.borrow().get(&name).unwrap(), Object::Num(2.));
+
+    let missing = Token::new(TokenType::Identifier, "b".to_string(), None, 3);
+    assert!(local.get(&missing).is_err());
+    assert!(local.assign(&missing, Object::Nil).is_err());
+
+    local.define("c", Object::Nil);
+    let mut names = local.names();
+    names.sort();
+    assert_eq!(names, vec!["a", "a", "c"]);
+}
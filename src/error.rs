@@ -1,14 +1,405 @@
-pub struct LoxError {
-    line: usize,
-    message: String,
+use std::fmt;
+
+use crate::token::{Span, Token, TokenType};
+
+/// Every error the interpreter can report, each with a stable code so
+/// that embedders and docs can refer to it. The first two digits of a
+/// code give the phase: `01` lexical, `02` parse, `03` resolve and `04`
+/// runtime. `W` codes are warnings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    UnexpectedCharacter,
+    UnterminatedString,
+    UnterminatedComment,
+    InvalidEscape,
+    MalformedNumber,
+    UnterminatedInterpolation,
+
+    ExpectedToken,
+    ExpectedExpression,
+    InvalidAssignmentTarget,
+    TooManyParameters,
+    TooManyArguments,
+    MisspelledKeyword,
+    NestedTooDeeply,
+
+    ReadInOwnInitializer,
+    AlreadyDeclared,
+    ReturnFromTopLevel,
+    ReturnValueFromInitializer,
+    ThisOutsideClass,
+    SuperOutsideClass,
+    SuperWithoutSuperclass,
+    InheritFromSelf,
+
+    UndefinedVariable,
+    UndefinedProperty,
+    OperandNotNumber,
+    InvalidOperands,
+    NotCallable,
+    ArityMismatch,
+    NotAnInstance,
+    SuperclassNotClass,
+    StackOverflow,
+
+    MixedScripts,
+    ConfusableIdentifier,
+
+    UnusedVariable,
+    UnreachableCode,
+    ShadowedVariable,
+    UninitializedRead,
+    NilComparison,
+}
+
+impl ErrorCode {
+    pub fn severity(self) -> Severity {
+        if self.as_str().starts_with('W') {
+            Severity::Warning
+        } else {
+            Severity::Error
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::UnexpectedCharacter => "E0101",
+            ErrorCode::UnterminatedString => "E0102",
+            ErrorCode::UnterminatedComment => "E0103",
+            ErrorCode::InvalidEscape => "E0104",
+            ErrorCode::MalformedNumber => "E0105",
+            ErrorCode::UnterminatedInterpolation => "E0106",
+
+            ErrorCode::ExpectedToken => "E0201",
+            ErrorCode::ExpectedExpression => "E0202",
+            ErrorCode::InvalidAssignmentTarget => "E0203",
+            ErrorCode::TooManyParameters => "E0204",
+            ErrorCode::TooManyArguments => "E0205",
+            ErrorCode::MisspelledKeyword => "E0206",
+            ErrorCode::NestedTooDeeply => "E0207",
+
+            ErrorCode::ReadInOwnInitializer => "E0301",
+            ErrorCode::AlreadyDeclared => "E0302",
+            ErrorCode::ReturnFromTopLevel => "E0303",
+            ErrorCode::ReturnValueFromInitializer => "E0304",
+            ErrorCode::ThisOutsideClass => "E0305",
+            ErrorCode::SuperOutsideClass => "E0306",
+            ErrorCode::SuperWithoutSuperclass => "E0307",
+            ErrorCode::InheritFromSelf => "E0308",
+
+            ErrorCode::UndefinedVariable => "E0401",
+            ErrorCode::UndefinedProperty => "E0402",
+            ErrorCode::OperandNotNumber => "E0403",
+            ErrorCode::InvalidOperands => "E0404",
+            ErrorCode::NotCallable => "E0405",
+            ErrorCode::ArityMismatch => "E0406",
+            ErrorCode::NotAnInstance => "E0407",
+            ErrorCode::SuperclassNotClass => "E0408",
+            ErrorCode::StackOverflow => "E0409",
+
+            ErrorCode::MixedScripts => "W0101",
+            ErrorCode::ConfusableIdentifier => "W0102",
+
+            ErrorCode::UnusedVariable => "W0301",
+            ErrorCode::UnreachableCode => "W0302",
+            ErrorCode::ShadowedVariable => "W0303",
+            ErrorCode::UninitializedRead => "W0304",
+            ErrorCode::NilComparison => "W0305",
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => f.write_str("Error"),
+            Severity::Warning => f.write_str("Warning"),
+        }
+    }
+}
+
+/// A secondary span shown next to the error, such as the opening brace a
+/// missing closing brace belongs to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+/// A call a runtime error unwound through.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    /// `f` or `Class.method`.
+    pub function: String,
+    /// The line of the call.
+    pub line: usize,
+}
+
+/// What went wrong and where.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub code: ErrorCode,
+    /// The code's severity, unless a warning was promoted to an error.
+    pub severity: Severity,
+    pub message: String,
+    pub line: usize,
+    /// 1-based column, when the error can be pinned to one.
+    pub column: Option<usize>,
+    pub span: Option<Span>,
+    /// The token a parse or resolve error was found at, as `'lexeme'` or
+    /// `end`.
+    pub at: Option<String>,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+    /// For runtime errors, the calls that were active, innermost first.
+    pub trace: Vec<Frame>,
+}
+
+/// Warnings are diagnostics on their own: they don't stop the script, so
+/// they are never a `LoxError`.
+impl Diagnostic {
+    pub fn new(
+        code: ErrorCode,
+        line: usize,
+        column: usize,
+        span: Span,
+        message: String,
+    ) -> Diagnostic {
+        Diagnostic {
+            code,
+            severity: code.severity(),
+            message,
+            line,
+            column: Some(column),
+            span: Some(span),
+            at: None,
+            labels: Vec::new(),
+            notes: Vec::new(),
+            help: None,
+            trace: Vec::new(),
+        }
+    }
+
+    /// A diagnostic found at `token`, which the one-line form names.
+    pub fn at_token(code: ErrorCode, token: &Token, message: String) -> Diagnostic {
+        let at = if token.token_type == TokenType::Eof {
+            "end".to_string()
+        } else {
+            format!("'{}'", token.lexeme)
+        };
+        Diagnostic {
+            at: Some(at),
+            ..Diagnostic::on_token(code, token, message)
+        }
+    }
+
+    fn on_token(code: ErrorCode, token: &Token, message: String) -> Diagnostic {
+        Diagnostic {
+            code,
+            severity: code.severity(),
+            message,
+            line: token.line,
+            column: (token.column > 0).then_some(token.column),
+            span: (token.column > 0).then_some(token.span),
+            at: None,
+            labels: Vec::new(),
+            notes: Vec::new(),
+            help: None,
+            trace: Vec::new(),
+        }
+    }
+
+    /// Points at another place in the source that explains the diagnostic.
+    pub fn with_label(mut self, span: Span, message: impl Into<String>) -> Diagnostic {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Diagnostic {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Diagnostic {
+        self.help = Some(help.into());
+        self
+    }
+
+    /// Turns a warning into an error, saying why in a note.
+    pub fn deny(mut self, reason: impl Into<String>) -> Diagnostic {
+        self.severity = Severity::Error;
+        self.with_note(reason)
+    }
+}
+
+/// The one-line form, `[line 1:7] Error[E0102]: Unterminated string.`,
+/// for embedders and tests; `render::Renderer` draws the full report.
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let position = match self.column {
+            Some(column) => format!("{}:{}", self.line, column),
+            None => self.line.to_string(),
+        };
+        let at = match &self.at {
+            Some(at) => format!(" at {}", at),
+            None => String::new(),
+        };
+        write!(
+            f,
+            "[line {}] {}[{}]{}: {}",
+            position, self.severity, self.code, at, self.message
+        )
+    }
+}
+
+/// An error from one of the phases a script goes through. The diagnostic
+/// is boxed to keep `Result`s small on the interpreter's hot paths.
+#[derive(Debug, Clone)]
+pub enum LoxError {
+    Lexical(Box<Diagnostic>),
+    Parse(Box<Diagnostic>),
+    Resolve(Box<Diagnostic>),
+    Runtime(Box<Diagnostic>),
 }
 
 impl LoxError {
-    pub fn report(&self, loc: String) {
-        eprintln!("[line {}] Error{}: {}", self.line, loc, self.message);
+    pub fn lexical(
+        code: ErrorCode,
+        line: usize,
+        column: usize,
+        span: Span,
+        message: String,
+    ) -> LoxError {
+        LoxError::Lexical(Box::new(Diagnostic::new(code, line, column, span, message)))
+    }
+
+    pub fn parse(code: ErrorCode, token: &Token, message: String) -> LoxError {
+        LoxError::Parse(Box::new(Diagnostic::at_token(code, token, message)))
+    }
+
+    pub fn resolve(code: ErrorCode, token: &Token, message: String) -> LoxError {
+        LoxError::Resolve(Box::new(Diagnostic::at_token(code, token, message)))
+    }
+
+    pub fn runtime(code: ErrorCode, token: &Token, message: String) -> LoxError {
+        LoxError::Runtime(Box::new(Diagnostic::on_token(code, token, message)))
+    }
+
+    /// Points at another place in the source that explains the error.
+    pub fn with_label(mut self, span: Span, message: impl Into<String>) -> LoxError {
+        self.diagnostic_mut().labels.push(Label {
+            span,
+            message: message.into(),
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> LoxError {
+        self.diagnostic_mut().notes.push(note.into());
+        self
     }
 
-    pub fn error(line: usize, message: String) -> LoxError {
-        LoxError { line, message }
+    pub fn with_help(mut self, help: impl Into<String>) -> LoxError {
+        self.diagnostic_mut().help = Some(help.into());
+        self
     }
+
+    /// Records that the error unwound through a call of `function` at
+    /// `call_site`.
+    pub fn with_frame(mut self, function: String, call_site: &Token) -> LoxError {
+        self.diagnostic_mut().trace.push(Frame {
+            function,
+            line: call_site.line,
+        });
+        self
+    }
+
+    pub fn diagnostic(&self) -> &Diagnostic {
+        match self {
+            LoxError::Lexical(diagnostic)
+            | LoxError::Parse(diagnostic)
+            | LoxError::Resolve(diagnostic)
+            | LoxError::Runtime(diagnostic) => diagnostic,
+        }
+    }
+
+    fn diagnostic_mut(&mut self) -> &mut Diagnostic {
+        match self {
+            LoxError::Lexical(diagnostic)
+            | LoxError::Parse(diagnostic)
+            | LoxError::Resolve(diagnostic)
+            | LoxError::Runtime(diagnostic) => diagnostic,
+        }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        self.diagnostic().code
+    }
+
+    pub fn span(&self) -> Option<Span> {
+        self.diagnostic().span
+    }
+}
+
+impl fmt::Display for LoxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.diagnostic().fmt(f)
+    }
+}
+
+impl std::error::Error for LoxError {}
+
+#[test]
+fn test_error_kinds() {
+    use crate::interpreter::Interpreter;
+    use crate::parse::Parser;
+    use crate::resolver::Resolver;
+    use crate::scanner::Scanner;
+
+    let (_, errors) = Scanner::new("print \"open").scan_tokens();
+    assert!(matches!(&errors[0], LoxError::Lexical(_)));
+    assert_eq!(errors[0].code(), ErrorCode::UnterminatedString);
+    assert_eq!(errors[0].span(), Some(Span::new(6, 11)));
+    assert_eq!(
+        errors[0].to_string(),
+        "[line 1:7] Error[E0102]: Unterminated string."
+    );
+
+    let errors = Parser::new(Scanner::new("print")).parse().unwrap_err();
+    assert!(matches!(&errors[0], LoxError::Parse(_)));
+    assert_eq!(
+        errors[0].to_string(),
+        "[line 1:6] Error[E0202] at end: Expect expression."
+    );
+
+    let statements = Parser::new(Scanner::new("return 1;")).parse().unwrap();
+    let errors = Resolver::default().resolve(&statements).unwrap_err();
+    assert!(matches!(&errors[0], LoxError::Resolve(_)));
+    assert_eq!(errors[0].code().as_str(), "E0303");
+
+    let statements = Parser::new(Scanner::new("print -nil;")).parse().unwrap();
+    let error = Interpreter::default().interpret(&statements).unwrap_err();
+    assert!(matches!(&error, LoxError::Runtime(_)));
+    assert_eq!(error.code(), ErrorCode::OperandNotNumber);
+    let error: Box<dyn std::error::Error> = Box::new(error);
+    assert_eq!(
+        error.to_string(),
+        "[line 1:7] Error[E0403]: Operand must be a number."
+    );
 }
@@ -1,3 +1,5 @@
+use std::cell::Cell;
+
 use crate::error::LoxError;
 use crate::literal::Object;
 use crate::token::Token;
@@ -5,9 +7,18 @@ use crate::token::Token;
 rlox_macros::define_ast! {
     "Expr",
     [
-        "Binary   : Expr left, Token operator, Expr right",
+        "Assign   : Token<'static> name, Expr value, Cell<Option<usize>> depth",
+        "Binary   : Expr left, Token<'static> operator, Expr right",
+        "Call     : Expr callee, Token<'static> paren, Vec<Expr> arguments",
+        "Get      : Expr object, Token<'static> name",
         "Grouping : Expr expression",
+        "Interpolation : Vec<Expr> parts",
         "Literal  : Object value",
-        "Unary    : Token operator, Expr right"
+        "Logical  : Expr left, Token<'static> operator, Expr right",
+        "Set      : Expr object, Token<'static> name, Expr value",
+        "Super    : Token<'static> keyword, Token<'static> method, Cell<Option<usize>> depth",
+        "This     : Token<'static> keyword, Cell<Option<usize>> depth",
+        "Unary    : Token<'static> operator, Expr right",
+        "Variable : Token<'static> name, Cell<Option<usize>> depth"
     ]
 }
@@ -0,0 +1,785 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::callable::{LoxCallable, LoxFunction, NativeFunction};
+use crate::class::{LoxClass, LoxInstance};
+use crate::environment::Environment;
+use crate::error::{ErrorCode, LoxError};
+use crate::expr::{self, Expr};
+use crate::literal::Object;
+use crate::stmt::{self, Stmt};
+use crate::suggest;
+use crate::token::{Token, TokenType};
+
+/// How much of the Rust stack Lox calls may use by default before a
+/// "Stack overflow." error, since each one recurses on it. This is safe
+/// on any thread, whose stack is 2 MiB unless configured otherwise;
+/// `Lox::main` runs on a bigger one and allows more.
+pub const DEFAULT_STACK_LIMIT: usize = 1 << 20;
+
+pub struct Interpreter {
+    globals: Rc<RefCell<Environment>>,
+    environment: Rc<RefCell<Environment>>,
+    /// The number of calls in progress.
+    call_depth: usize,
+    /// The stack address at which the outermost call in progress started.
+    stack_base: usize,
+    /// The bytes of stack calls may use below `stack_base`.
+    stack_limit: usize,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        let globals = Rc::new(RefCell::new(Environment::default()));
+        for native in NativeFunction::all() {
+            globals
+                .borrow_mut()
+                .define(native.name, Object::Callable(Rc::new(native)));
+        }
+        Interpreter {
+            environment: Rc::clone(&globals),
+            globals,
+            call_depth: 0,
+            stack_base: 0,
+            stack_limit: DEFAULT_STACK_LIMIT,
+        }
+    }
+}
+
+impl Interpreter {
+    /// An interpreter allowing calls to use `stack_limit` bytes of stack,
+    /// for a thread known to have that much to spare.
+    pub fn with_stack_limit(stack_limit: usize) -> Interpreter {
+        Interpreter {
+            stack_limit,
+            ..Interpreter::default()
+        }
+    }
+
+    pub fn interpret(&mut self, statements: &[Stmt]) -> Result<(), LoxError> {
+        self.execute_statements(statements)?;
+        Ok(())
+    }
+
+    /// Executes a statement. `Some(value)` means a `return` statement is
+    /// unwinding to the enclosing function call.
+    fn execute(&mut self, stmt: &Stmt) -> Result<Option<Object>, LoxError> {
+        stmt.accept(self)
+    }
+
+    fn execute_statements(&mut self, statements: &[Stmt]) -> Result<Option<Object>, LoxError> {
+        for statement in statements {
+            if let Some(value) = self.execute(statement)? {
+                return Ok(Some(value));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Runs `statements` in `environment`, restoring the current
+    /// environment afterwards even if a statement fails.
+    pub fn execute_block(
+        &mut self,
+        statements: &[Stmt],
+        environment: Environment,
+    ) -> Result<Option<Object>, LoxError> {
+        let previous = std::mem::replace(&mut self.environment, Rc::new(RefCell::new(environment)));
+        let result = self.execute_statements(statements);
+        self.environment = previous;
+        result
+    }
+
+    fn evaluate(&mut self, expr: &Expr) -> Result<Object, LoxError> {
+        expr.accept(self)
+    }
+
+    /// Looks a name up at the depth the resolver recorded for it, or in the
+    /// globals when it was left unresolved.
+    fn look_up_variable(&self, name: &Token, depth: Option<usize>) -> Result<Object, LoxError> {
+        match depth {
+            Some(distance) => self.environment.borrow().get_at(distance, name),
+            None => {
+                let result = self.globals.borrow().get(name);
+                result.map_err(|err| self.suggest_name(err, name))
+            }
+        }
+    }
+
+    /// Adds a "did you mean" to an undefined variable error: a name in
+    /// scope or, failing that, a keyword that is a value like the variable
+    /// would have been.
+    fn suggest_name(&self, err: LoxError, name: &Token) -> LoxError {
+        let names = self.environment.borrow().names();
+        if let Some(similar) = suggest::closest(&name.lexeme, names.iter().map(String::as_str)) {
+            return err.with_help(format!("Did you mean '{}'?", similar));
+        }
+        match suggest::value_keyword(&name.lexeme) {
+            Some(keyword) => err.with_help(format!("Did you mean the keyword '{}'?", keyword)),
+            None => err,
+        }
+    }
+
+    /// `false` and `nil` are falsey, everything else is truthy.
+    fn is_truthy(object: &Object) -> bool {
+        !matches!(object, Object::Nil | Object::False)
+    }
+
+    fn check_number_operand(operator: &Token, operand: &Object) -> Result<f64, LoxError> {
+        match operand {
+            Object::Num(num) => Ok(*num),
+            _ => Err(LoxError::runtime(
+                ErrorCode::OperandNotNumber,
+                operator,
+                "Operand must be a number.".to_string(),
+            )),
+        }
+    }
+
+    fn check_number_operands(
+        operator: &Token,
+        left: &Object,
+        right: &Object,
+    ) -> Result<(f64, f64), LoxError> {
+        match (left, right) {
+            (Object::Num(left), Object::Num(right)) => Ok((*left, *right)),
+            _ => Err(LoxError::runtime(
+                ErrorCode::OperandNotNumber,
+                operator,
+                "Operands must be numbers.".to_string(),
+            )),
+        }
+    }
+
+    /// Converts a value to the text `print` shows for it.
+    pub fn stringify(object: &Object) -> String {
+        match object {
+            Object::String(string) => string.clone(),
+            object => object.to_string(),
+        }
+    }
+}
+
+impl expr::Visitor for Interpreter {
+    type Res = Object;
+
+    fn visit_assign(&mut self, expr: &expr::Assign) -> Result<Object, LoxError> {
+        let value = self.evaluate(&expr.value)?;
+        match expr.depth.get() {
+            Some(distance) => {
+                self.environment
+                    .borrow_mut()
+                    .assign_at(distance, &expr.name, value.clone())?
+            }
+            None => {
+                let result = self.globals.borrow_mut().assign(&expr.name, value.clone());
+                result.map_err(|err| self.suggest_name(err, &expr.name))?
+            }
+        }
+        Ok(value)
+    }
+
+    fn visit_binary(&mut self, expr: &expr::Binary) -> Result<Object, LoxError> {
+        let left = self.evaluate(&expr.left)?;
+        let right = self.evaluate(&expr.right)?;
+        let operator = &expr.operator;
+
+        let value = match operator.token_type {
+            TokenType::Greater => {
+                let (left, right) = Self::check_number_operands(operator, &left, &right)?;
+                Object::from(left > right)
+            }
+            TokenType::GreaterEqual => {
+                let (left, right) = Self::check_number_operands(operator, &left, &right)?;
+                Object::from(left >= right)
+            }
+            TokenType::Less => {
+                let (left, right) = Self::check_number_operands(operator, &left, &right)?;
+                Object::from(left < right)
+            }
+            TokenType::LessEqual => {
+                let (left, right) = Self::check_number_operands(operator, &left, &right)?;
+                Object::from(left <= right)
+            }
+            TokenType::BangEqual => Object::from(left != right),
+            TokenType::EqualEqual => Object::from(left == right),
+            TokenType::Minus => {
+                let (left, right) = Self::check_number_operands(operator, &left, &right)?;
+                Object::Num(left - right)
+            }
+            TokenType::Slash => {
+                let (left, right) = Self::check_number_operands(operator, &left, &right)?;
+                Object::Num(left / right)
+            }
+            TokenType::Star => {
+                let (left, right) = Self::check_number_operands(operator, &left, &right)?;
+                Object::Num(left * right)
+            }
+            TokenType::Plus => match (left, right) {
+                (Object::Num(left), Object::Num(right)) => Object::Num(left + right),
+                (Object::String(left), Object::String(right)) => Object::String(left + &right),
+                _ => {
+                    return Err(LoxError::runtime(
+                        ErrorCode::InvalidOperands,
+                        operator,
+                        "Operands must be two numbers or two strings.".to_string(),
+                    ))
+                }
+            },
+            _ => unreachable!("invalid binary operator: {}", operator.lexeme),
+        };
+        Ok(value)
+    }
+
+    fn visit_call(&mut self, expr: &expr::Call) -> Result<Object, LoxError> {
+        let callee = self.evaluate(&expr.callee)?;
+
+        let mut arguments = Vec::with_capacity(expr.arguments.len());
+        for argument in expr.arguments.iter() {
+            arguments.push(self.evaluate(argument)?);
+        }
+
+        let function: Rc<dyn LoxCallable> = match callee {
+            Object::Callable(function) => function,
+            Object::Class(class) => class,
+            _ => {
+                return Err(LoxError::runtime(
+                    ErrorCode::NotCallable,
+                    &expr.paren,
+                    "Can only call functions and classes.".to_string(),
+                ))
+            }
+        };
+        if arguments.len() != function.arity() {
+            return Err(LoxError::runtime(
+                ErrorCode::ArityMismatch,
+                &expr.paren,
+                format!(
+                    "Expected {} arguments but got {}.",
+                    function.arity(),
+                    arguments.len()
+                ),
+            ));
+        }
+        let here = stack_address();
+        if self.call_depth == 0 {
+            self.stack_base = here;
+        } else if self.stack_base.abs_diff(here) > self.stack_limit {
+            return Err(LoxError::runtime(
+                ErrorCode::StackOverflow,
+                &expr.paren,
+                "Stack overflow.".to_string(),
+            ));
+        }
+
+        self.call_depth += 1;
+        let result = Rc::clone(&function).call(self, arguments);
+        self.call_depth -= 1;
+        result.map_err(|err| err.with_frame(function.name(), &expr.paren))
+    }
+
+    fn visit_get(&mut self, expr: &expr::Get) -> Result<Object, LoxError> {
+        match self.evaluate(&expr.object)? {
+            Object::Instance(instance) => LoxInstance::get(&instance, &expr.name),
+            _ => Err(LoxError::runtime(
+                ErrorCode::NotAnInstance,
+                &expr.name,
+                "Only instances have properties.".to_string(),
+            )),
+        }
+    }
+
+    fn visit_grouping(&mut self, expr: &expr::Grouping) -> Result<Object, LoxError> {
+        self.evaluate(&expr.expression)
+    }
+
+    fn visit_interpolation(&mut self, expr: &expr::Interpolation) -> Result<Object, LoxError> {
+        let mut value = String::new();
+        for part in expr.parts.iter() {
+            let part = self.evaluate(part)?;
+            value.push_str(&Interpreter::stringify(&part));
+        }
+        Ok(Object::String(value))
+    }
+
+    fn visit_literal(&mut self, expr: &expr::Literal) -> Result<Object, LoxError> {
+        Ok(expr.value.clone())
+    }
+
+    fn visit_logical(&mut self, expr: &expr::Logical) -> Result<Object, LoxError> {
+        let left = self.evaluate(&expr.left)?;
+
+        if expr.operator.token_type == TokenType::Or {
+            if Self::is_truthy(&left) {
+                return Ok(left);
+            }
+        } else if !Self::is_truthy(&left) {
+            return Ok(left);
+        }
+
+        self.evaluate(&expr.right)
+    }
+
+    fn visit_set(&mut self, expr: &expr::Set) -> Result<Object, LoxError> {
+        let Object::Instance(instance) = self.evaluate(&expr.object)? else {
+            return Err(LoxError::runtime(
+                ErrorCode::NotAnInstance,
+                &expr.name,
+                "Only instances have fields.".to_string(),
+            ));
+        };
+
+        let value = self.evaluate(&expr.value)?;
+        instance.borrow_mut().set(&expr.name, value.clone());
+        Ok(value)
+    }
+
+    fn visit_super(&mut self, expr: &expr::Super) -> Result<Object, LoxError> {
+        // The resolver only records a depth for `super` inside a subclass.
+        let distance = expr.depth.get().unwrap_or_default();
+        let superclass = self.environment.borrow().get_at(distance, &expr.keyword)?;
+        // `this` always lives in the scope just inside the one holding `super`.
+        let object = self
+            .environment
+            .borrow()
+            .lookup_at(distance.saturating_sub(1), "this");
+
+        let (Object::Class(superclass), Some(Object::Instance(object))) = (superclass, object)
+        else {
+            unreachable!("resolver binds 'super' and 'this' around every subclass method");
+        };
+
+        match superclass.find_method(&expr.method.lexeme) {
+            Some(method) => Ok(Object::Callable(Rc::new(method.bind(object)))),
+            None => {
+                let similar = suggest::closest(&expr.method.lexeme, superclass.method_names());
+                Err(LoxClass::undefined_property(&expr.method, similar))
+            }
+        }
+    }
+
+    fn visit_this(&mut self, expr: &expr::This) -> Result<Object, LoxError> {
+        self.look_up_variable(&expr.keyword, expr.depth.get())
+    }
+
+    fn visit_unary(&mut self, expr: &expr::Unary) -> Result<Object, LoxError> {
+        let right = self.evaluate(&expr.right)?;
+
+        match expr.operator.token_type {
+            TokenType::Bang => Ok(Object::from(!Self::is_truthy(&right))),
+            TokenType::Minus => {
+                let right = Self::check_number_operand(&expr.operator, &right)?;
+                Ok(Object::Num(-right))
+            }
+            _ => unreachable!("invalid unary operator: {}", expr.operator.lexeme),
+        }
+    }
+
+    fn visit_variable(&mut self, expr: &expr::Variable) -> Result<Object, LoxError> {
+        self.look_up_variable(&expr.name, expr.depth.get())
+    }
+}
+
+impl stmt::Visitor for Interpreter {
+    type Res = Option<Object>;
+
+    fn visit_block(&mut self, stmt: &stmt::Block) -> Result<Option<Object>, LoxError> {
+        let environment = Environment::new(Rc::clone(&self.environment));
+        self.execute_block(&stmt.statements, environment)
+    }
+
+    fn visit_class(&mut self, stmt: &stmt::Class) -> Result<Option<Object>, LoxError> {
+        let superclass = match &stmt.superclass {
+            Some(superclass) => {
+                match self.look_up_variable(&superclass.name, superclass.depth.get())? {
+                    Object::Class(class) => Some(class),
+                    _ => {
+                        return Err(LoxError::runtime(
+                            ErrorCode::SuperclassNotClass,
+                            &superclass.name,
+                            "Superclass must be a class.".to_string(),
+                        ))
+                    }
+                }
+            }
+            None => None,
+        };
+
+        self.environment
+            .borrow_mut()
+            .define(&stmt.name.lexeme, Object::Nil);
+
+        let enclosing = Rc::clone(&self.environment);
+        if let Some(superclass) = &superclass {
+            let mut environment = Environment::new(Rc::clone(&enclosing));
+            environment.define("super", Object::Class(Rc::clone(superclass)));
+            self.environment = Rc::new(RefCell::new(environment));
+        }
+
+        let class_name: Rc<str> = Rc::from(stmt.name.lexeme.as_ref());
+        let mut methods = HashMap::new();
+        for method in stmt.methods.iter() {
+            let function = LoxFunction::new(
+                method.clone(),
+                Rc::clone(&self.environment),
+                method.name.lexeme == "init",
+            )
+            .in_class(Rc::clone(&class_name));
+            methods.insert(method.name.lexeme.to_string(), Rc::new(function));
+        }
+
+        let class = LoxClass::new(stmt.name.lexeme.to_string(), superclass, methods);
+        self.environment = enclosing;
+        self.environment
+            .borrow_mut()
+            .assign(&stmt.name, Object::Class(Rc::new(class)))?;
+        Ok(None)
+    }
+
+    fn visit_expression(&mut self, stmt: &stmt::Expression) -> Result<Option<Object>, LoxError> {
+        self.evaluate(&stmt.expression)?;
+        Ok(None)
+    }
+
+    fn visit_function(&mut self, stmt: &stmt::Function) -> Result<Option<Object>, LoxError> {
+        let function = LoxFunction::new(stmt.clone(), Rc::clone(&self.environment), false);
+        self.environment
+            .borrow_mut()
+            .define(&stmt.name.lexeme, Object::Callable(Rc::new(function)));
+        Ok(None)
+    }
+
+    fn visit_if(&mut self, stmt: &stmt::If) -> Result<Option<Object>, LoxError> {
+        if Self::is_truthy(&self.evaluate(&stmt.condition)?) {
+            self.execute(&stmt.then_branch)
+        } else if let Some(else_branch) = &stmt.else_branch {
+            self.execute(else_branch)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn visit_print(&mut self, stmt: &stmt::Print) -> Result<Option<Object>, LoxError> {
+        let value = self.evaluate(&stmt.expression)?;
+        println!("{}", Self::stringify(&value));
+        Ok(None)
+    }
+
+    fn visit_return(&mut self, stmt: &stmt::Return) -> Result<Option<Object>, LoxError> {
+        let value = match &stmt.value {
+            Some(value) => self.evaluate(value)?,
+            None => Object::Nil,
+        };
+        Ok(Some(value))
+    }
+
+    fn visit_var(&mut self, stmt: &stmt::Var) -> Result<Option<Object>, LoxError> {
+        let value = match &stmt.initializer {
+            Some(initializer) => self.evaluate(initializer)?,
+            None => Object::Nil,
+        };
+        self.environment
+            .borrow_mut()
+            .define(&stmt.name.lexeme, value);
+        Ok(None)
+    }
+
+    fn visit_while(&mut self, stmt: &stmt::While) -> Result<Option<Object>, LoxError> {
+        while Self::is_truthy(&self.evaluate(&stmt.condition)?) {
+            if let Some(value) = self.execute(&stmt.body)? {
+                return Ok(Some(value));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// An address in the caller's stack frame, to measure how deep the stack
+/// has grown between two calls.
+#[inline(always)]
+fn stack_address() -> usize {
+    let marker = 0u8;
+    std::hint::black_box(&marker) as *const u8 as usize
+}
+
+#[test]
+fn test_interpreter() {
+    use crate::parse::Parser;
+    use crate::scanner::Scanner;
+
+    fn eval(source: &str) -> Result<Object, LoxError> {
+        let source = format!("{};", source);
+        let statements = Parser::new(Scanner::new(&source)).parse().unwrap();
+        match &statements[0] {
+            Stmt::Expression(stmt) => Interpreter::default().evaluate(&stmt.expression),
+            _ => unreachable!(),
+        }
+    }
+
+    assert_eq!(eval("1 + 2 * 3").unwrap(), Object::Num(7.));
+    assert_eq!(
+        eval("\"a\" + \"b\"").unwrap(),
+        Object::String("ab".to_string())
+    );
+    assert_eq!(eval("!nil == true").unwrap(), Object::True);
+    assert_eq!(eval("1 == \"1\"").unwrap(), Object::False);
+    assert_eq!(eval("nil or 0").unwrap(), Object::Num(0.));
+    assert!(eval("1 + \"a\"").is_err());
+    assert!(eval("-\"a\"").is_err());
+}
+
+#[cfg(test)]
+impl Interpreter {
+    /// Scans, parses, resolves and runs `source` against this interpreter.
+    fn run_source(&mut self, source: &str) -> Result<(), LoxError> {
+        use crate::parse::Parser;
+        use crate::resolver::Resolver;
+        use crate::scanner::Scanner;
+
+        let statements = Parser::new(Scanner::new(source)).parse().unwrap();
+        Resolver::default().resolve(&statements).unwrap();
+        self.interpret(&statements)
+    }
+
+    fn global(&self, name: &str) -> Object {
+        let name = Token::new(TokenType::Identifier, name.to_string(), None, 1);
+        self.globals.borrow().get(&name).unwrap()
+    }
+}
+
+#[test]
+fn test_interpreter_scopes() {
+    let mut interpreter = Interpreter::default();
+    let source = "var a = 1; var b = 2; { var a = 10; b = a + b; } var a = a + 1; c = 1;";
+    let err = interpreter.run_source(source).unwrap_err();
+    assert!(format!("{:?}", err).contains("Undefined variable 'c'."));
+    assert_eq!(interpreter.global("a"), Object::Num(2.));
+    assert_eq!(interpreter.global("b"), Object::Num(12.));
+}
+
+#[test]
+fn test_interpreter_functions() {
+    let mut interpreter = Interpreter::default();
+    let source = "
+        fun fib(n) { if (n < 2) return n; return fib(n - 1) + fib(n - 2); }
+        fun makeCounter() { var i = 0; fun count() { i = i + 1; return i; } return count; }
+        var counter = makeCounter();
+        counter();
+        var a = fib(10);
+        var b = counter();
+        var c = clock() > 0;
+    ";
+    interpreter.run_source(source).unwrap();
+    assert_eq!(interpreter.global("a"), Object::Num(55.));
+    assert_eq!(interpreter.global("b"), Object::Num(2.));
+    assert_eq!(interpreter.global("c"), Object::True);
+
+    let err = interpreter.run_source("fib(1, 2);").unwrap_err();
+    assert!(format!("{:?}", err).contains("Expected 1 arguments but got 2."));
+}
+
+#[test]
+fn test_interpreter_closure_binding() {
+    let mut interpreter = Interpreter::default();
+    let source = "
+        var a = \"global\";
+        var r1; var r2;
+        {
+            fun show() { return a; }
+            r1 = show();
+            var a = \"block\";
+            r2 = show();
+        }
+    ";
+    interpreter.run_source(source).unwrap();
+    assert_eq!(
+        interpreter.global("r1"),
+        Object::String("global".to_string())
+    );
+    assert_eq!(
+        interpreter.global("r2"),
+        Object::String("global".to_string())
+    );
+}
+
+#[test]
+fn test_interpreter_classes() {
+    let mut interpreter = Interpreter::default();
+    let source = "
+        class Counter {
+            init(start) { this.count = start; }
+            increment() { this.count = this.count + 1; return this; }
+        }
+        var counter = Counter(10);
+        var method = counter.increment;
+        method();
+        var a = counter.increment().count;
+        var b = counter.init(1) == counter;
+        var c = counter.count;
+    ";
+    interpreter.run_source(source).unwrap();
+    assert_eq!(interpreter.global("a"), Object::Num(12.));
+    assert_eq!(interpreter.global("b"), Object::True);
+    assert_eq!(interpreter.global("c"), Object::Num(1.));
+
+    let err = interpreter.run_source("counter.missing;").unwrap_err();
+    assert!(format!("{:?}", err).contains("Undefined property 'missing'."));
+    let err = interpreter.run_source("Counter();").unwrap_err();
+    assert!(format!("{:?}", err).contains("Expected 1 arguments but got 0."));
+}
+
+#[test]
+fn test_interpreter_inheritance() {
+    let mut interpreter = Interpreter::default();
+    let source = "
+        class A {
+            init(name) { this.name = name; }
+            method() { return \"A method\"; }
+            greet() { return \"A \" + this.name; }
+        }
+        class B < A {
+            method() { return \"B method\"; }
+            test() { return super.method(); }
+            greet() { return super.greet() + \" via B\"; }
+        }
+        class C < B {}
+        var c = C(\"c\");
+        var a = c.test();
+        var b = c.method();
+        var greeting = c.greet();
+    ";
+    interpreter.run_source(source).unwrap();
+    assert_eq!(
+        interpreter.global("a"),
+        Object::String("A method".to_string())
+    );
+    assert_eq!(
+        interpreter.global("b"),
+        Object::String("B method".to_string())
+    );
+    assert_eq!(
+        interpreter.global("greeting"),
+        Object::String("A c via B".to_string())
+    );
+
+    let err = interpreter
+        .run_source("var NotAClass = 1; class D < NotAClass {}")
+        .unwrap_err();
+    assert!(format!("{:?}", err).contains("Superclass must be a class."));
+}
+
+#[test]
+fn test_interpreter_interpolation() {
+    let mut interpreter = Interpreter::default();
+    let source = r#"
+        var name = "Lox";
+        var count = 2;
+        var a = "Hello ${name}, you have ${count + 1} items";
+        var b = "${"nested ${nil} ${true}"}${1.5}";
+    "#;
+    interpreter.run_source(source).unwrap();
+    assert_eq!(
+        interpreter.global("a"),
+        Object::String("Hello Lox, you have 3 items".to_string())
+    );
+    assert_eq!(
+        interpreter.global("b"),
+        Object::String("nested nil true1.5".to_string())
+    );
+}
+
+#[test]
+fn test_interpreter_stack_trace() {
+    use crate::error::Frame;
+
+    let frame = |function: &str, line: usize| Frame {
+        function: function.to_string(),
+        line,
+    };
+    let mut interpreter = Interpreter::default();
+    let source = "
+        class Shape {
+            init(sides) { this.sides = sides; this.check(); }
+            check() { return -this.name; }
+        }
+        fun make() {
+            return Shape(3);
+        }
+        make();
+    ";
+    let err = interpreter.run_source(source).unwrap_err();
+    assert_eq!(
+        err.diagnostic().trace,
+        vec![
+            frame("Shape.check", 3),
+            frame("Shape.init", 7),
+            frame("make", 9)
+        ]
+    );
+
+    // Runaway recursion is stopped before the Rust stack overflows, on
+    // the test's own thread with its default stack.
+    let mut interpreter = Interpreter::default();
+    let err = interpreter
+        .run_source("fun f(n) { return f(n + 1); }\nf(0);")
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::StackOverflow);
+    assert!(err.diagnostic().trace.len() > 10);
+    assert_eq!(err.diagnostic().trace.last(), Some(&frame("f", 2)));
+    assert_eq!(interpreter.call_depth, 0);
+
+    // A bigger limit, on a thread with the stack for it, allows more.
+    let deep = std::thread::Builder::new()
+        .stack_size(64 << 20)
+        .spawn(|| {
+            let source = "fun f(n) { if (n > 0) return f(n - 1); return 0; }\nf(2000);";
+            Interpreter::with_stack_limit(63 << 20).run_source(source)
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+    assert!(deep.is_ok());
+}
+
+#[test]
+fn test_interpreter_suggestions() {
+    let help = |source: &str| {
+        let err = Interpreter::default().run_source(source).unwrap_err();
+        err.diagnostic().help.clone()
+    };
+    let help_for = |suggestion: &str| Some(suggestion.to_string());
+
+    assert_eq!(
+        help("var counter = 1; fun f(total) { print totl + countr; } f(1);"),
+        help_for("Did you mean 'total'?")
+    );
+    assert_eq!(
+        help("var counter = 1; countr = 2;"),
+        help_for("Did you mean 'counter'?")
+    );
+    assert_eq!(
+        help("fun f() { return nill; } f();"),
+        help_for("Did you mean the keyword 'nil'?")
+    );
+    assert_eq!(help("print undefined;"), None);
+    // Only keywords that are values make sense in place of a variable.
+    assert_eq!(help("print i;"), None);
+    assert_eq!(help("var x = whiel;"), None);
+
+    let source = "
+        class A { speak() {} }
+        class B < A { init() { this.volume = 1; } shout() { super.speek(); } }
+    ";
+    assert_eq!(
+        help(&format!("{} B().volum;", source)),
+        help_for("Did you mean 'volume'?")
+    );
+    assert_eq!(
+        help(&format!("{} B().speka();", source)),
+        help_for("Did you mean 'speak'?")
+    );
+    assert_eq!(
+        help(&format!("{} B().shout();", source)),
+        help_for("Did you mean 'speak'?")
+    );
+}
@@ -0,0 +1,94 @@
+//! Just enough JSON to write diagnostics, so the crate needs no
+//! dependencies for it.
+
+use std::fmt;
+
+pub enum Json {
+    Null,
+    Number(usize),
+    String(String),
+    Array(Vec<Json>),
+    /// Keys are written in the order given.
+    Object(Vec<(&'static str, Json)>),
+}
+
+impl From<&str> for Json {
+    fn from(value: &str) -> Json {
+        Json::String(value.to_string())
+    }
+}
+
+impl From<usize> for Json {
+    fn from(value: usize) -> Json {
+        Json::Number(value)
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(value: Option<T>) -> Json {
+        value.map_or(Json::Null, Into::into)
+    }
+}
+
+/// Writes compact JSON, on a single line.
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => f.write_str("null"),
+            Json::Number(number) => write!(f, "{}", number),
+            Json::String(string) => write_string(f, string),
+            Json::Array(items) => {
+                f.write_str("[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                f.write_str("]")
+            }
+            Json::Object(members) => {
+                f.write_str("{")?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, string: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for ch in string.chars() {
+        match ch {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            ch if ch < ' ' || ch == '\u{7f}' => write!(f, "\\u{:04x}", ch as u32)?,
+            ch => write!(f, "{}", ch)?,
+        }
+    }
+    f.write_str("\"")
+}
+
+#[test]
+fn test_json() {
+    let value = Json::Object(vec![
+        ("message", Json::from("Unexpected character: \"\u{1}\".\n")),
+        ("file", Json::from("C:\\main.lox")),
+        ("span", Json::from(None::<usize>)),
+        ("notes", Json::Array(vec![Json::from("π"), Json::from(3)])),
+        ("labels", Json::Array(Vec::new())),
+    ]);
+    assert_eq!(
+        value.to_string(),
+        r#"{"message":"Unexpected character: \"\u0001\".\n","file":"C:\\main.lox","span":null,"notes":["π",3],"labels":[]}"#
+    );
+}
@@ -1,9 +1,20 @@
+mod callable;
+mod class;
+mod environment;
 pub mod error;
 mod expr;
+mod interpreter;
+mod json;
+mod lint;
 mod literal;
 pub mod lox;
 mod macros;
+mod parse;
 mod printer;
-mod scanner;
-mod token;
-mod parse;
\ No newline at end of file
+pub mod render;
+mod resolver;
+pub mod scanner;
+mod stmt;
+mod suggest;
+pub mod token;
+mod unicode;
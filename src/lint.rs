@@ -0,0 +1,259 @@
+//! Warning levels. Every warning has a name, such as `unused`, and can be
+//! allowed, left as a warning or denied, which turns it into an error.
+//! On the command line that is `--allow=unused`, `--warn=unused` or
+//! `--deny=unused`, where `warnings` stands for all of them. In the source
+//! it is a comment:
+//!
+//! ```text
+//! fun callback(event, unused) { // lox:allow(unused)
+//! ```
+//!
+//! A comment at the end of a line applies to that line, one on a line of
+//! its own to the next line of code, and one before any code to the whole
+//! file. Comments take precedence over the command line.
+
+use crate::error::{Diagnostic, ErrorCode};
+use crate::scanner::Scanner;
+use crate::token::{Trivia, TriviaKind};
+
+/// Every warning, by the name used to control it.
+const LINTS: &[(&str, ErrorCode)] = &[
+    ("mixed_scripts", ErrorCode::MixedScripts),
+    ("confusable", ErrorCode::ConfusableIdentifier),
+    ("unused", ErrorCode::UnusedVariable),
+    ("unreachable", ErrorCode::UnreachableCode),
+    ("shadowed", ErrorCode::ShadowedVariable),
+    ("uninitialized", ErrorCode::UninitializedRead),
+    ("nil_comparison", ErrorCode::NilComparison),
+];
+
+/// The name that stands for every warning.
+const ALL: &str = "warnings";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl Level {
+    fn from_name(name: &str) -> Option<Level> {
+        match name {
+            "allow" => Some(Level::Allow),
+            "warn" => Some(Level::Warn),
+            "deny" => Some(Level::Deny),
+            _ => None,
+        }
+    }
+}
+
+/// Parses a `--allow=NAME`, `--warn=NAME` or `--deny=NAME` argument.
+pub fn flag(arg: &str) -> Option<(Level, &str)> {
+    let (level, name) = arg.strip_prefix("--")?.split_once('=')?;
+    Some((Level::from_name(level)?, name))
+}
+
+fn name(code: ErrorCode) -> Option<&'static str> {
+    LINTS
+        .iter()
+        .find(|(_, lint)| *lint == code)
+        .map(|(name, _)| *name)
+}
+
+/// A `// lox:allow(...)`, `// lox:warn(...)` or `// lox:deny(...)`
+/// comment.
+struct Directive {
+    level: Level,
+    names: Vec<String>,
+    /// The line the comment applies to, or `None` for the whole file.
+    target: Option<usize>,
+    /// The line the comment is on.
+    line: usize,
+}
+
+impl Directive {
+    fn parse(comment: &str, target: Option<usize>, line: usize) -> Option<Directive> {
+        let rest = comment.strip_prefix("//")?.trim().strip_prefix("lox:")?;
+        let (level, names) = rest.split_once('(')?;
+        let names = names.trim_end().strip_suffix(')')?;
+        Some(Directive {
+            level: Level::from_name(level.trim())?,
+            names: names
+                .split(',')
+                .map(|name| name.trim().to_string())
+                .filter(|name| !name.is_empty())
+                .collect(),
+            target,
+            line,
+        })
+    }
+
+    /// Finds the directives in `source`, file-wide ones first.
+    fn find(source: &str) -> Vec<Directive> {
+        // Comments are only kept by a lossless scan, so don't pay for one
+        // unless there is something to find.
+        if !source.contains("lox:") {
+            return Vec::new();
+        }
+        let line_of = |offset: usize| source[..offset].matches('\n').count() + 1;
+        let mut directives = Vec::new();
+        for (index, token) in Scanner::lossless(source).flatten().enumerate() {
+            let comments = |trivia: &[Trivia]| {
+                trivia
+                    .iter()
+                    .filter(|trivia| trivia.kind == TriviaKind::LineComment)
+                    .map(|trivia| (trivia.text.to_string(), line_of(trivia.span.start)))
+                    .collect::<Vec<_>>()
+            };
+            let target = (index > 0).then_some(token.line);
+            for (text, line) in comments(token.leading_trivia()) {
+                directives.extend(Directive::parse(&text, target, line));
+            }
+            for (text, line) in comments(token.trailing_trivia()) {
+                directives.extend(Directive::parse(&text, Some(token.line), line));
+            }
+        }
+        directives.sort_by_key(|directive| directive.target.is_some());
+        directives
+    }
+}
+
+/// The levels set on the command line.
+#[derive(Debug, Default, Clone)]
+pub struct Lints {
+    /// In the order given, so later settings win.
+    levels: Vec<(String, Level)>,
+}
+
+impl Lints {
+    /// Sets the level of the warning `name`, or of every warning for
+    /// `warnings`. Returns `false` if there is no such warning.
+    pub fn set(&mut self, name: &str, level: Level) -> bool {
+        if name != ALL && !LINTS.iter().any(|(lint, _)| *lint == name) {
+            return false;
+        }
+        self.levels.push((name.to_string(), level));
+        true
+    }
+
+    /// Drops the allowed warnings and turns the denied ones into errors,
+    /// according to the command line and the comments in `source`.
+    pub fn apply(&self, source: &str, warnings: Vec<Diagnostic>) -> Vec<Diagnostic> {
+        let directives = Directive::find(source);
+        let mut explained = Vec::new();
+        let mut kept = Vec::new();
+        for warning in warnings {
+            let Some(name) = name(warning.code) else {
+                kept.push(warning);
+                continue;
+            };
+            let applies = |lint: &str| lint == name || lint == ALL;
+
+            let mut level = Level::Warn;
+            let mut reason = None;
+            for (lint, set) in self.levels.iter().filter(|(lint, _)| applies(lint)) {
+                level = *set;
+                reason = Some(format!("Denied by `--deny={}` on the command line.", lint));
+            }
+            let line = warning.line;
+            for directive in directives.iter() {
+                if directive.target.is_some_and(|target| target != line) {
+                    continue;
+                }
+                if let Some(lint) = directive.names.iter().find(|lint| applies(lint)) {
+                    level = directive.level;
+                    reason = Some(format!(
+                        "Denied by `// lox:deny({})` on line {}.",
+                        lint, directive.line
+                    ));
+                }
+            }
+
+            match level {
+                Level::Allow => {}
+                Level::Warn if !explained.contains(&name) => {
+                    explained.push(name);
+                    kept.push(warning.with_note(format!(
+                        "`// lox:allow({})` or `--allow={}` silences this warning.",
+                        name, name
+                    )));
+                }
+                Level::Warn => kept.push(warning),
+                Level::Deny => kept.push(warning.deny(reason.unwrap_or_default())),
+            }
+        }
+        kept
+    }
+}
+
+#[test]
+fn test_lints() {
+    use crate::error::Severity;
+    use crate::token::Span;
+
+    let warning = |code: ErrorCode, line: usize| {
+        Diagnostic::new(code, line, 1, Span::new(0, 1), "Careful.".to_string())
+    };
+    let levels = |warnings: &[Diagnostic]| {
+        warnings
+            .iter()
+            .map(|warning| (warning.line, warning.severity))
+            .collect::<Vec<_>>()
+    };
+
+    assert!(Lints::default().set("warnings", Level::Deny));
+    assert!(!Lints::default().set("unusde", Level::Allow));
+    assert_eq!(flag("--deny=unused"), Some((Level::Deny, "unused")));
+    assert_eq!(flag("--forbid=unused"), None);
+
+    let mut lints = Lints::default();
+    lints.set("warnings", Level::Deny);
+    lints.set("shadowed", Level::Warn);
+    let source = "\
+var a = 1; // lox:allow(unused)
+// lox:warn(unused, nil_comparison)
+var b = 2;
+var c = 3;";
+    let warnings = lints.apply(
+        source,
+        vec![
+            warning(ErrorCode::UnusedVariable, 1),
+            warning(ErrorCode::UnusedVariable, 3),
+            warning(ErrorCode::UnusedVariable, 4),
+            warning(ErrorCode::ShadowedVariable, 4),
+        ],
+    );
+    assert_eq!(
+        levels(&warnings),
+        vec![
+            (3, Severity::Warning),
+            (4, Severity::Error),
+            (4, Severity::Warning)
+        ]
+    );
+    assert_eq!(
+        warnings[1].notes,
+        vec!["Denied by `--deny=warnings` on the command line."]
+    );
+
+    // A comment before any code applies to the whole file, and is
+    // overridden by one on the line itself.
+    let source = "// lox:deny(unused)\n\nvar a;\nvar b; // lox:allow(warnings)\n";
+    let warnings = Lints::default().apply(
+        source,
+        vec![
+            warning(ErrorCode::UnusedVariable, 3),
+            warning(ErrorCode::UnusedVariable, 4),
+            warning(ErrorCode::ShadowedVariable, 3),
+        ],
+    );
+    assert_eq!(
+        levels(&warnings),
+        vec![(3, Severity::Error), (3, Severity::Warning)]
+    );
+    assert_eq!(
+        warnings[0].notes,
+        vec!["Denied by `// lox:deny(unused)` on line 1."]
+    );
+}
@@ -1,14 +1,38 @@
 #![allow(dead_code)]
 
+use std::cell::RefCell;
 use std::fmt;
+use std::rc::Rc;
 
-#[derive(Debug, Clone, PartialEq)]
+use crate::callable::LoxCallable;
+use crate::class::{LoxClass, LoxInstance};
+
+#[derive(Debug, Clone)]
 pub enum Object {
     Num(f64),
     String(String),
     Nil,
     True,
     False,
+    Callable(Rc<dyn LoxCallable>),
+    Class(Rc<LoxClass>),
+    Instance(Rc<RefCell<LoxInstance>>),
+}
+
+impl PartialEq for Object {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Object::Num(left), Object::Num(right)) => left == right,
+            (Object::String(left), Object::String(right)) => left == right,
+            (Object::Nil, Object::Nil)
+            | (Object::True, Object::True)
+            | (Object::False, Object::False) => true,
+            (Object::Callable(left), Object::Callable(right)) => Rc::ptr_eq(left, right),
+            (Object::Class(left), Object::Class(right)) => Rc::ptr_eq(left, right),
+            (Object::Instance(left), Object::Instance(right)) => Rc::ptr_eq(left, right),
+            _ => false,
+        }
+    }
 }
 
 impl fmt::Display for Object {
@@ -19,6 +43,19 @@ impl fmt::Display for Object {
             Object::Nil => write!(f, "nil"),
             Object::True => write!(f, "true"),
             Object::False => write!(f, "false"),
+            Object::Callable(callable) => write!(f, "{}", callable),
+            Object::Class(class) => write!(f, "{}", class),
+            Object::Instance(instance) => write!(f, "{}", instance.borrow()),
+        }
+    }
+}
+
+impl From<bool> for Object {
+    fn from(value: bool) -> Self {
+        if value {
+            Object::True
+        } else {
+            Object::False
         }
     }
 }
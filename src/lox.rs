@@ -2,54 +2,154 @@ use std::env;
 use std::io;
 use std::io::BufRead;
 use std::io::Write;
+use std::panic;
 use std::process::exit;
+use std::thread;
 
-use crate::error::LoxError;
+use crate::error::{LoxError, Severity};
+use crate::interpreter::{Interpreter, DEFAULT_STACK_LIMIT};
+use crate::lint::{self, Lints};
+use crate::parse::Parser;
+use crate::render::{self, ErrorFormat, Renderer};
+use crate::resolver::Resolver;
 use crate::scanner::Scanner;
+use crate::unicode::Identifiers;
 
+/// The stack `Lox::main` runs on: Lox calls recurse on the Rust stack, and
+/// this leaves room for deep recursion even in debug builds.
+const STACK_SIZE: usize = 1 << 28;
+
+#[derive(Default)]
 pub struct Lox {
+    interpreter: Interpreter,
+    error_format: ErrorFormat,
+    lints: Lints,
     had_error: bool,
-}
-impl Default for Lox {
-    fn default() -> Self {
-        Lox { had_error: false }
-    }
+    had_runtime_error: bool,
 }
 
 impl Lox {
+    /// Runs the command line on a thread of its own with a big stack, so
+    /// scripts may recurse deeply. `parse` runs it on the current thread,
+    /// where the interpreter allows only as much recursion as is safe on
+    /// any stack.
+    pub fn main() -> io::Result<()> {
+        thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn(|| {
+                Lox {
+                    // Leave room for the frames below the interpreter.
+                    interpreter: Interpreter::with_stack_limit(STACK_SIZE - DEFAULT_STACK_LIMIT),
+                    ..Lox::default()
+                }
+                .parse()
+            })?
+            .join()
+            .unwrap_or_else(|payload| panic::resume_unwind(payload))
+    }
+
     pub fn parse(&mut self) -> io::Result<()> {
-        let args = env::args();
-        if args.len() > 2 {
-            eprintln!("Usage: jlox [script]");
-            exit(64);
-        } else if args.len() == 2 {
-            self.run_file(args.last().unwrap())?;
+        let mut scripts = Vec::new();
+        for arg in env::args().skip(1) {
+            if let Some(name) = arg.strip_prefix("--error-format=") {
+                match ErrorFormat::from_name(name) {
+                    Some(format) => self.error_format = format,
+                    None => Lox::usage(),
+                }
+            } else if let Some((level, name)) = lint::flag(&arg) {
+                if !self.lints.set(name, level) {
+                    eprintln!("Unknown warning '{}'.", name);
+                    exit(64);
+                }
+            } else if arg.starts_with("--") {
+                Lox::usage();
+            } else {
+                scripts.push(arg);
+            }
+        }
+        if scripts.len() > 1 {
+            Lox::usage();
+        } else if let Some(script) = scripts.pop() {
+            self.run_file(script)?;
             if self.had_error {
                 exit(65);
             }
+            if self.had_runtime_error {
+                exit(70);
+            }
         } else {
             self.run_prompt()?;
         }
         Ok(())
     }
 
+    fn usage() -> ! {
+        eprintln!(
+            "Usage: jlox [--error-format=human|json] [--allow|--warn|--deny=WARNING] [script]"
+        );
+        exit(64);
+    }
+
+    /// Renders errors in the chosen format, colored when that is for a
+    /// terminal.
+    fn renderer<'a>(&self, file: &'a str, source: Option<&'a str>) -> Renderer<'a> {
+        let color = self.error_format == ErrorFormat::Human && render::stderr_color();
+        Renderer::new(file, source)
+            .color(color)
+            .format(self.error_format)
+    }
+
     fn run_file(&mut self, path: impl AsRef<str>) -> io::Result<()> {
-        let buf = std::fs::read_to_string(path.as_ref())?;
-        if let Err(err) = self.run(buf) {
-            err.report("".to_string());
-            self.had_error = true;
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)?;
+        let renderer = self.renderer(path, Some(&source));
+        if let Err(err) = self.run(&source, &renderer) {
+            eprint!("{}", renderer.render(err.diagnostic()));
+            self.had_runtime_error = true;
         }
         Ok(())
     }
 
-    fn run(&mut self, source: String) -> Result<(), LoxError> {
-        let mut scanner = Scanner::new(source);
-        let tokens = scanner.scan_tokens()?;
-        for token in tokens.iter() {
-            println!("{}", token);
+    /// Scans, parses and resolves `source`, reporting warnings and static
+    /// errors through `renderer`, then interprets it unless there were
+    /// errors or denied warnings. A runtime error is returned for the
+    /// caller to report.
+    fn run(&mut self, source: &str, renderer: &Renderer) -> Result<(), LoxError> {
+        let mut identifiers = Identifiers::default();
+        let tokens = Scanner::new(source).inspect(|token| {
+            if let Ok(token) = token {
+                identifiers.add(token);
+            }
+        });
+        let (statements, mut errors) = match Parser::new(tokens).parse() {
+            Ok(statements) => (statements, Vec::new()),
+            Err(errors) => (Vec::new(), errors),
+        };
+        let mut warnings = identifiers.check();
+        if errors.is_empty() {
+            let mut resolver = Resolver::default();
+            if let Err(resolve_errors) = resolver.resolve(&statements) {
+                errors = resolve_errors;
+            }
+            warnings.extend(resolver.warnings());
+        }
+
+        let warnings = self.lints.apply(source, warnings);
+        for warning in warnings.iter() {
+            eprint!("{}", renderer.render(warning));
+        }
+        let denied = warnings
+            .iter()
+            .any(|warning| warning.severity == Severity::Error);
+        if !errors.is_empty() || denied {
+            for err in errors.iter() {
+                eprint!("{}", renderer.render(err.diagnostic()));
+            }
+            self.had_error = true;
+            return Ok(());
         }
 
-        Ok(())
+        self.interpreter.interpret(&statements)
     }
 
     fn run_prompt(&mut self) -> io::Result<()> {
@@ -60,10 +160,16 @@ impl Lox {
                 if line.is_empty() {
                     break;
                 }
-                if let Err(err) = self.run(line) {
-                    self.had_error = false;
-                    err.report("".to_string());
+                let renderer = self.renderer("<stdin>", Some(&line));
+                if let Err(err) = self.run(&line, &renderer) {
+                    // The error may be in a function declared on an earlier
+                    // line, whose source is gone, so there's no snippet.
+                    eprint!(
+                        "{}",
+                        self.renderer("<stdin>", None).render(err.diagnostic())
+                    );
                 }
+                self.had_error = false;
             } else {
                 break;
             }
@@ -1,5 +1,5 @@
 use rlox::lox::Lox;
 
 fn main() -> std::io::Result<()> {
-    Lox::default().parse()
+    Lox::main()
 }
@@ -1,156 +1,1111 @@
-// use crate::expr;
-// use crate::expr::*;
-// use crate::literal::Object;
-// use crate::token::Token;
-// use crate::token::TokenType;
-
-// pub struct Parser {
-//     tokens: Vec<Token>,
-//     current: usize,
-// }
-
-// impl Parser {
-//     pub fn new(tokens: Vec<Token>) -> Self {
-//         Parser { tokens, current: 0 }
-//     }
-
-//     fn expression<R>(&self) -> Box<dyn Expr<R>> {
-//         self.equality()
-//     }
-
-//     fn equality<R>(&self) -> Box<dyn Expr<R>> {
-//         let mut expr = self.comparison();
-
-//         while self.match_token_type(vec![TokenType::BangEqual, TokenType::EqualEqual]) {
-//             let operator = self.previous();
-//             let right = self.comparison();
-//             expr = Box::new(Binary {
-//                 expr,
-//                 operator,
-//                 right,
-//             });
-//         }
-//         expr
-//     }
-
-//     fn match_token_type(&mut self, types: Vec<TokenType>) -> bool {
-//         for ty in types.into_iter() {
-//             if self.check(ty) {
-//                 self.advance();
-//                 return true;
-//             }
-//         }
-
-//         false
-//     }
-
-//     fn check(&self, ty: TokenType) -> bool {
-//         if self.is_at_end() {
-//             return false;
-//         }
-
-//         self.peek().token_type == ty
-//     }
-
-//     fn advance(&mut self) -> Token {
-//         if !self.is_at_end() {
-//             self.current += 1;
-//         }
-//         self.previous()
-//     }
-
-//     fn is_at_end(&self) -> bool {
-//         self.peek().token_type == TokenType::Eof
-//     }
-
-//     fn peek(&self) -> Token {
-//         self.tokens.get(self.current).unwrap().clone()
-//     }
-
-//     fn previous(&self) -> Token {
-//         self.tokens.get(self.current - 1).unwrap().clone()
-//     }
-
-//     fn comparison<R>(&self) -> Box<dyn Expr<R>> {
-//         let mut expr = self.term();
-//         while self.match_token_type(vec![
-//             TokenType::Greater,
-//             TokenType::GreaterEqual,
-//             TokenType::Less,
-//             TokenType::LessEqual,
-//         ]) {
-//             let operator = self.previous();
-//             let right = self.term();
-//             expr = Box::new(Binary {
-//                 expr,
-//                 operator,
-//                 right,
-//             });
-//         }
-//         expr
-//     }
-
-//     fn term<R>(&self) -> Box<dyn Expr<R>> {
-//         let mut expr = self.factor();
-//         while self.match_token_type(vec![TokenType::Minus, TokenType::Plus]) {
-//             let operator = self.previous();
-//             let right = self.factor();
-//             expr = Box::new(Binary {
-//                 expr,
-//                 operator,
-//                 right,
-//             });
-//         }
-//         expr
-//     }
-
-//     fn factor<R>(&self) -> Box<dyn Expr<R>> {
-//         let mut expr = self.unary();
-
-//         while self.match_token_type(vec![TokenType::Slash, TokenType::Star]) {
-//             let operator = self.previous();
-//             let right = self.unary();
-//             expr = Box::new(Binary {
-//                 expr,
-//                 operator,
-//                 right,
-//             })
-//         }
-//         expr
-//     }
-
-//     fn unary<R>(&self) -> Box<dyn Expr<R>> {
-//         if self.match_token_type(vec![TokenType::Bang, TokenType::Minus]) {
-//             let operator = self.previous();
-//             let right = self.unary();
-//             return Box::new(Unary { operator, right });
-//         }
-//         self.primary()
-//     }
-
-//     fn primary<R>(&self) -> Box<dyn Expr<R>> {
-//         if self.match_token_type(vec![TokenType::False]) {
-//             return Box::new(Literal {
-//                 value: Object::False,
-//             });
-//         }
-//         if self.match_token_type(vec![TokenType::True]) {
-//             return Box::new(Literal {
-//                 value: Object::True,
-//             });
-//         }
-//         if self.match_token_type(vec![TokenType::Nil]) {
-//             return Box::new(Literal { value: Object::Nil });
-//         }
-//         if self.match_token_type(vec![TokenType::Number, TokenType::String]) {
-//             return Box::new(Literal {
-//                 value: self.previous().literal.unwrap(),
-//             });
-//         }
-//         if self.match_token_type(vec![TokenType::LeftParen]) {
-//             let expr = self.expression();
-//             self.consume(TokenType::RightParen, "Expect ')' after expression.");
-//             return Box::new(Grouping { expression: expr });
-//         }
-//     }
-// }
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::rc::Rc;
+
+use crate::error::{ErrorCode, LoxError};
+use crate::expr::*;
+use crate::literal::Object;
+use crate::stmt::{self, Stmt};
+use crate::suggest;
+use crate::token::TokenType;
+use crate::token::{Span, Token};
+
+pub struct Parser<'src, I> {
+    tokens: I,
+    /// The token being looked at.
+    current: Token<'src>,
+    /// The token just consumed.
+    previous: Token<'src>,
+    errors: Vec<LoxError>,
+    /// The doc comment right before `current`, if any.
+    doc: Option<String>,
+    /// How deeply the tree being built is nested so far.
+    depth: usize,
+}
+
+/// How deeply statements and expressions may nest. Every later pass walks
+/// the tree recursively, so this is what keeps any input from overflowing
+/// the stack.
+const MAX_DEPTH: usize = 256;
+
+impl<'src, I> Parser<'src, I>
+where
+    I: Iterator<Item = Result<Token<'src>, LoxError>>,
+{
+    /// Tokens are pulled from `tokens` one at a time as the parser goes,
+    /// so the whole stream is never held in memory. Lexical errors coming
+    /// out of it are reported with the syntax errors.
+    pub fn new(tokens: I) -> Self {
+        let mut parser = Parser {
+            tokens,
+            current: Token::eof(1),
+            previous: Token::eof(1),
+            errors: Vec::new(),
+            doc: None,
+            depth: 0,
+        };
+        parser.current = parser.next_token();
+        parser
+    }
+
+    /// Pulls the next token, setting aside doc comments: the declaration
+    /// that follows one picks it up, anywhere else it is ignored like any
+    /// other comment.
+    fn next_token(&mut self) -> Token<'src> {
+        let mut doc: Vec<String> = Vec::new();
+        loop {
+            match self.tokens.next() {
+                Some(Ok(token)) if token.token_type == TokenType::DocComment => {
+                    if let Some(Object::String(text)) = token.literal {
+                        doc.push(text);
+                    }
+                }
+                Some(Ok(token)) => {
+                    self.doc = (!doc.is_empty()).then(|| doc.join("\n"));
+                    return token;
+                }
+                Some(Err(err)) => self.errors.push(err),
+                None => return Token::eof(self.current.line),
+            }
+        }
+    }
+
+    /// Parses the token stream as a list of declarations. A syntax error
+    /// does not stop the parser: it is recorded, the parser synchronizes to
+    /// the next statement boundary and carries on, and all errors are
+    /// returned together. Only nesting past `MAX_DEPTH` ends the parse.
+    pub fn parse(&mut self) -> Result<Vec<Stmt>, Vec<LoxError>> {
+        let mut statements = Vec::new();
+        while !self.is_at_end() {
+            match self.declaration() {
+                Ok(stmt) => statements.push(stmt),
+                Err(err) => {
+                    // Past too deep a nesting there is no telling where it
+                    // ends, and every closing delimiter would be an error.
+                    let give_up = err.code() == ErrorCode::NestedTooDeeply;
+                    if !self.after_lexical_error() {
+                        self.errors.push(err);
+                    }
+                    if give_up {
+                        break;
+                    }
+                    self.depth = 0;
+                    self.synchronize();
+                }
+            }
+        }
+
+        if self.errors.is_empty() {
+            Ok(statements)
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
+    }
+
+    /// Whether the parser is at or just past an `Error` token. A syntax
+    /// error there is almost certainly caused by the lexical error, which
+    /// has been reported already.
+    fn after_lexical_error(&self) -> bool {
+        self.previous.token_type == TokenType::Error || self.current.token_type == TokenType::Error
+    }
+
+    fn declaration(&mut self) -> Result<Stmt, LoxError> {
+        let doc = self.doc.take();
+        if self.match_token_type(&[TokenType::Class]) {
+            return self.class_declaration(doc);
+        }
+        if self.match_token_type(&[TokenType::Fun]) {
+            return Ok(Stmt::from(self.function("function", doc)?));
+        }
+        if self.match_token_type(&[TokenType::Var]) {
+            return self.var_declaration(doc);
+        }
+        self.statement()
+    }
+
+    fn class_declaration(&mut self, doc: Option<String>) -> Result<Stmt, LoxError> {
+        let name = self
+            .consume(TokenType::Identifier, "Expect class name.")?
+            .to_static();
+
+        let mut superclass = None;
+        if self.match_token_type(&[TokenType::Less]) {
+            let name = self.consume(TokenType::Identifier, "Expect superclass name.")?;
+            superclass = Some(Variable {
+                name: name.to_static(),
+                depth: Cell::new(None),
+            });
+        }
+
+        let open = self
+            .consume(TokenType::LeftBrace, "Expect '{' before class body.")?
+            .span;
+
+        let mut methods = Vec::new();
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            let doc = self.doc.take();
+            methods.push(self.function("method", doc)?);
+        }
+
+        self.consume_closing(TokenType::RightBrace, open, "Expect '}' after class body.")?;
+
+        Ok(Stmt::from(stmt::Class {
+            name,
+            superclass,
+            methods,
+            doc,
+        }))
+    }
+
+    fn function(&mut self, kind: &str, doc: Option<String>) -> Result<stmt::Function, LoxError> {
+        let name = self
+            .consume(TokenType::Identifier, &format!("Expect {} name.", kind))?
+            .to_static();
+        let open = self
+            .consume(
+                TokenType::LeftParen,
+                &format!("Expect '(' after {} name.", kind),
+            )?
+            .span;
+        let mut params = Vec::new();
+        if !self.check(TokenType::RightParen) {
+            loop {
+                if params.len() >= 255 {
+                    let err = LoxError::parse(
+                        ErrorCode::TooManyParameters,
+                        self.peek(),
+                        "Can't have more than 255 parameters.".to_string(),
+                    );
+                    self.errors.push(err);
+                }
+                params.push(
+                    self.consume(TokenType::Identifier, "Expect parameter name.")?
+                        .to_static(),
+                );
+                if !self.match_token_type(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+        self.consume_closing(TokenType::RightParen, open, "Expect ')' after parameters.")?;
+
+        self.consume(
+            TokenType::LeftBrace,
+            &format!("Expect '{{' before {} body.", kind),
+        )?;
+        let body = self.block()?;
+        Ok(stmt::Function {
+            name,
+            params,
+            body: Rc::new(body),
+            doc,
+        })
+    }
+
+    fn var_declaration(&mut self, doc: Option<String>) -> Result<Stmt, LoxError> {
+        let name = self
+            .consume(TokenType::Identifier, "Expect variable name.")?
+            .to_static();
+
+        let initializer = if self.match_token_type(&[TokenType::Equal]) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+
+        self.consume(
+            TokenType::Semicolon,
+            "Expect ';' after variable declaration.",
+        )?;
+        Ok(Stmt::from(stmt::Var {
+            name,
+            initializer,
+            doc,
+        }))
+    }
+
+    fn statement(&mut self) -> Result<Stmt, LoxError> {
+        if self.match_token_type(&[TokenType::For]) {
+            return self.for_statement();
+        }
+        if self.match_token_type(&[TokenType::If]) {
+            return self.if_statement();
+        }
+        if self.match_token_type(&[TokenType::Print]) {
+            return self.print_statement();
+        }
+        if self.match_token_type(&[TokenType::Return]) {
+            return self.return_statement();
+        }
+        if self.match_token_type(&[TokenType::While]) {
+            return self.while_statement();
+        }
+        if self.match_token_type(&[TokenType::LeftBrace]) {
+            return Ok(Stmt::from(stmt::Block {
+                statements: self.block()?,
+            }));
+        }
+        self.expression_statement()
+    }
+
+    /// `for` has no node of its own: it is desugared into a `While` loop
+    /// wrapped in blocks for the initializer and increment.
+    fn for_statement(&mut self) -> Result<Stmt, LoxError> {
+        let keyword = self.previous().to_static();
+        self.consume(TokenType::LeftParen, "Expect '(' after 'for'.")?;
+
+        let initializer = if self.match_token_type(&[TokenType::Semicolon]) {
+            None
+        } else if self.match_token_type(&[TokenType::Var]) {
+            Some(self.var_declaration(None)?)
+        } else {
+            Some(self.expression_statement()?)
+        };
+
+        let condition = if !self.check(TokenType::Semicolon) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+        self.consume(TokenType::Semicolon, "Expect ';' after loop condition.")?;
+
+        let increment = if !self.check(TokenType::RightParen) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+        self.consume(TokenType::RightParen, "Expect ')' after for clauses.")?;
+
+        let mut body = self.nested("Statement", Self::statement)?;
+
+        if let Some(increment) = increment {
+            body = Stmt::from(stmt::Block {
+                statements: vec![
+                    body,
+                    Stmt::from(stmt::Expression {
+                        expression: increment,
+                    }),
+                ],
+            });
+        }
+
+        let condition = condition.unwrap_or(Expr::from(Literal {
+            value: Object::True,
+        }));
+        body = Stmt::from(stmt::While {
+            keyword,
+            condition,
+            body: Box::new(body),
+        });
+
+        if let Some(initializer) = initializer {
+            body = Stmt::from(stmt::Block {
+                statements: vec![initializer, body],
+            });
+        }
+
+        Ok(body)
+    }
+
+    fn if_statement(&mut self) -> Result<Stmt, LoxError> {
+        let keyword = self.previous().to_static();
+        self.consume(TokenType::LeftParen, "Expect '(' after 'if'.")?;
+        let condition = self.expression()?;
+        self.consume(TokenType::RightParen, "Expect ')' after if condition.")?;
+
+        let then_branch = Box::new(self.nested("Statement", Self::statement)?);
+        let else_branch = if self.match_token_type(&[TokenType::Else]) {
+            Some(Box::new(self.nested("Statement", Self::statement)?))
+        } else {
+            None
+        };
+
+        Ok(Stmt::from(stmt::If {
+            keyword,
+            condition,
+            then_branch,
+            else_branch,
+        }))
+    }
+
+    fn print_statement(&mut self) -> Result<Stmt, LoxError> {
+        let keyword = self.previous().to_static();
+        let expression = self.expression()?;
+        self.consume(TokenType::Semicolon, "Expect ';' after value.")?;
+        Ok(Stmt::from(stmt::Print {
+            keyword,
+            expression,
+        }))
+    }
+
+    fn return_statement(&mut self) -> Result<Stmt, LoxError> {
+        let keyword = self.previous().to_static();
+        let value = if !self.check(TokenType::Semicolon) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+
+        self.consume(TokenType::Semicolon, "Expect ';' after return value.")?;
+        Ok(Stmt::from(stmt::Return { keyword, value }))
+    }
+
+    fn while_statement(&mut self) -> Result<Stmt, LoxError> {
+        let keyword = self.previous().to_static();
+        self.consume(TokenType::LeftParen, "Expect '(' after 'while'.")?;
+        let condition = self.expression()?;
+        self.consume(TokenType::RightParen, "Expect ')' after condition.")?;
+        let body = Box::new(self.nested("Statement", Self::statement)?);
+
+        Ok(Stmt::from(stmt::While {
+            keyword,
+            condition,
+            body,
+        }))
+    }
+
+    fn block(&mut self) -> Result<Vec<Stmt>, LoxError> {
+        let open = self.previous.span;
+        let mut statements = Vec::new();
+
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            statements.push(self.nested("Statement", Self::declaration)?);
+        }
+
+        self.consume_closing(TokenType::RightBrace, open, "Expect '}' after block.")?;
+        Ok(statements)
+    }
+
+    fn expression_statement(&mut self) -> Result<Stmt, LoxError> {
+        let expression = self.expression()?;
+        if let Err(err) = self.consume(TokenType::Semicolon, "Expect ';' after expression.") {
+            return Err(Self::misspelled_keyword(err, &expression));
+        }
+        if let Expr::Variable(variable) = &expression {
+            if let Some(keyword) = suggest::statement_keyword(&variable.name.lexeme) {
+                // Report without bailing out: the statement itself parsed.
+                let err = LoxError::parse(
+                    ErrorCode::MisspelledKeyword,
+                    &variable.name,
+                    "Expect statement.".to_string(),
+                );
+                self.errors
+                    .push(Self::keyword_hint(err, &variable.name, keyword));
+            }
+        }
+        Ok(Stmt::from(stmt::Expression { expression }))
+    }
+
+    /// A statement like `retrun x;` or `whiel (x) {}` parses as an
+    /// expression starting with the misspelled keyword until it fails, so
+    /// point at that.
+    fn misspelled_keyword(err: LoxError, expression: &Expr) -> LoxError {
+        let name = match expression {
+            Expr::Variable(variable) => &variable.name,
+            Expr::Call(call) => match call.callee.as_ref() {
+                Expr::Variable(variable) => &variable.name,
+                _ => return err,
+            },
+            _ => return err,
+        };
+        match suggest::keyword(&name.lexeme) {
+            Some(keyword) => Self::keyword_hint(err, name, keyword),
+            None => err,
+        }
+    }
+
+    fn keyword_hint(err: LoxError, name: &Token, keyword: &str) -> LoxError {
+        err.with_label(name.span, format!("'{}' is not a keyword", name.lexeme))
+            .with_help(format!("Did you mean the keyword '{}'?", keyword))
+    }
+
+    /// Counts one more level of nesting in the tree, failing past
+    /// `MAX_DEPTH`. Loops building left-nested chains like `a + b + c`
+    /// call this once per link and reset the depth when done.
+    fn nest(&mut self, what: &str) -> Result<(), LoxError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(LoxError::parse(
+                ErrorCode::NestedTooDeeply,
+                self.peek(),
+                format!("{} nested too deeply.", what),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Runs `parse` one level deeper.
+    fn nested<T>(
+        &mut self,
+        what: &str,
+        parse: impl FnOnce(&mut Self) -> Result<T, LoxError>,
+    ) -> Result<T, LoxError> {
+        let depth = self.depth;
+        self.nest(what)?;
+        let result = parse(self);
+        self.depth = depth;
+        result
+    }
+
+    /// Discards tokens until it reaches what is probably the start of the
+    /// next statement.
+    fn synchronize(&mut self) {
+        self.advance();
+
+        while !self.is_at_end() {
+            if self.previous().token_type == TokenType::Semicolon {
+                return;
+            }
+
+            match self.peek().token_type {
+                TokenType::Class
+                | TokenType::Fun
+                | TokenType::Var
+                | TokenType::For
+                | TokenType::If
+                | TokenType::While
+                | TokenType::Print
+                | TokenType::Return => return,
+                _ => {}
+            }
+
+            self.advance();
+        }
+    }
+
+    fn expression(&mut self) -> Result<Expr, LoxError> {
+        self.nested("Expression", Self::assignment)
+    }
+
+    fn assignment(&mut self) -> Result<Expr, LoxError> {
+        let expr = self.or()?;
+
+        if self.match_token_type(&[TokenType::Equal]) {
+            let equals = self.previous().clone();
+            let value = Box::new(self.nested("Expression", Self::assignment)?);
+
+            match expr {
+                Expr::Variable(Variable { name, .. }) => {
+                    return Ok(Expr::from(Assign {
+                        name,
+                        value,
+                        depth: Cell::new(None),
+                    }));
+                }
+                Expr::Get(Get { object, name }) => {
+                    return Ok(Expr::from(Set {
+                        object,
+                        name,
+                        value,
+                    }));
+                }
+                _ => {
+                    // Report without bailing out: the parser is not confused.
+                    self.errors.push(LoxError::parse(
+                        ErrorCode::InvalidAssignmentTarget,
+                        &equals,
+                        "Invalid assignment target.".to_string(),
+                    ));
+                }
+            }
+        }
+
+        Ok(expr)
+    }
+
+    fn or(&mut self) -> Result<Expr, LoxError> {
+        let depth = self.depth;
+        let mut expr = self.and()?;
+
+        while self.match_token_type(&[TokenType::Or]) {
+            self.nest("Expression")?;
+            let operator = self.previous().to_static();
+            let right = self.and()?;
+            expr = Expr::from(Logical {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            });
+        }
+        self.depth = depth;
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, LoxError> {
+        let depth = self.depth;
+        let mut expr = self.equality()?;
+
+        while self.match_token_type(&[TokenType::And]) {
+            self.nest("Expression")?;
+            let operator = self.previous().to_static();
+            let right = self.equality()?;
+            expr = Expr::from(Logical {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            });
+        }
+        self.depth = depth;
+        Ok(expr)
+    }
+
+    fn equality(&mut self) -> Result<Expr, LoxError> {
+        let depth = self.depth;
+        let mut expr = self.comparison()?;
+
+        while self.match_token_type(&[TokenType::BangEqual, TokenType::EqualEqual]) {
+            self.nest("Expression")?;
+            let operator = self.previous().to_static();
+            let right = self.comparison()?;
+            expr = Expr::from(Binary {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            });
+        }
+        self.depth = depth;
+        Ok(expr)
+    }
+
+    fn match_token_type(&mut self, types: &[TokenType]) -> bool {
+        for ty in types.iter() {
+            if self.check(*ty) {
+                self.advance();
+                return true;
+            }
+        }
+
+        false
+    }
+
+    fn consume(&mut self, ty: TokenType, message: &str) -> Result<&Token<'src>, LoxError> {
+        if self.check(ty) {
+            return Ok(self.advance());
+        }
+        Err(LoxError::parse(
+            ErrorCode::ExpectedToken,
+            self.peek(),
+            message.to_string(),
+        ))
+    }
+
+    /// Like `consume`, for the delimiter closing the one at `open`, which
+    /// the error points back to.
+    fn consume_closing(
+        &mut self,
+        ty: TokenType,
+        open: Span,
+        message: &str,
+    ) -> Result<&Token<'src>, LoxError> {
+        self.consume(ty, message)
+            .map_err(|err| err.with_label(open, "unclosed delimiter"))
+    }
+
+    fn check(&self, ty: TokenType) -> bool {
+        if self.is_at_end() {
+            return false;
+        }
+
+        self.peek().token_type == ty
+    }
+
+    fn advance(&mut self) -> &Token<'src> {
+        if !self.is_at_end() {
+            let next = self.next_token();
+            self.previous = std::mem::replace(&mut self.current, next);
+        }
+        self.previous()
+    }
+
+    fn is_at_end(&self) -> bool {
+        self.peek().token_type == TokenType::Eof
+    }
+
+    fn peek(&self) -> &Token<'src> {
+        &self.current
+    }
+
+    /// The token just consumed. Nodes that keep it detach it from the
+    /// source with `to_static`.
+    fn previous(&self) -> &Token<'src> {
+        &self.previous
+    }
+
+    fn comparison(&mut self) -> Result<Expr, LoxError> {
+        let depth = self.depth;
+        let mut expr = self.term()?;
+        while self.match_token_type(&[
+            TokenType::Greater,
+            TokenType::GreaterEqual,
+            TokenType::Less,
+            TokenType::LessEqual,
+        ]) {
+            self.nest("Expression")?;
+            let operator = self.previous().to_static();
+            let right = self.term()?;
+            expr = Expr::from(Binary {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            });
+        }
+        self.depth = depth;
+        Ok(expr)
+    }
+
+    fn term(&mut self) -> Result<Expr, LoxError> {
+        let depth = self.depth;
+        let mut expr = self.factor()?;
+        while self.match_token_type(&[TokenType::Minus, TokenType::Plus]) {
+            self.nest("Expression")?;
+            let operator = self.previous().to_static();
+            let right = self.factor()?;
+            expr = Expr::from(Binary {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            });
+        }
+        self.depth = depth;
+        Ok(expr)
+    }
+
+    fn factor(&mut self) -> Result<Expr, LoxError> {
+        let depth = self.depth;
+        let mut expr = self.unary()?;
+
+        while self.match_token_type(&[TokenType::Slash, TokenType::Star]) {
+            self.nest("Expression")?;
+            let operator = self.previous().to_static();
+            let right = self.unary()?;
+            expr = Expr::from(Binary {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            });
+        }
+        self.depth = depth;
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, LoxError> {
+        if self.match_token_type(&[TokenType::Bang, TokenType::Minus]) {
+            let operator = self.previous().to_static();
+            let right = self.nested("Expression", Self::unary)?;
+            return Ok(Expr::from(Unary {
+                operator,
+                right: Box::new(right),
+            }));
+        }
+        self.call()
+    }
+
+    fn call(&mut self) -> Result<Expr, LoxError> {
+        let depth = self.depth;
+        let mut expr = self.primary()?;
+
+        loop {
+            if self.match_token_type(&[TokenType::LeftParen]) {
+                self.nest("Expression")?;
+                expr = self.finish_call(expr)?;
+            } else if self.match_token_type(&[TokenType::Dot]) {
+                self.nest("Expression")?;
+                let name = self
+                    .consume(TokenType::Identifier, "Expect property name after '.'.")?
+                    .to_static();
+                expr = Expr::from(Get {
+                    object: Box::new(expr),
+                    name,
+                });
+            } else {
+                break;
+            }
+        }
+        self.depth = depth;
+        Ok(expr)
+    }
+
+    fn finish_call(&mut self, callee: Expr) -> Result<Expr, LoxError> {
+        let open = self.previous.span;
+        let mut arguments = Vec::new();
+        if !self.check(TokenType::RightParen) {
+            loop {
+                if arguments.len() >= 255 {
+                    let err = LoxError::parse(
+                        ErrorCode::TooManyArguments,
+                        self.peek(),
+                        "Can't have more than 255 arguments.".to_string(),
+                    );
+                    self.errors.push(err);
+                }
+                arguments.push(self.expression()?);
+                if !self.match_token_type(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+
+        let paren = self
+            .consume_closing(TokenType::RightParen, open, "Expect ')' after arguments.")?
+            .to_static();
+        Ok(Expr::from(Call {
+            callee: Box::new(callee),
+            paren,
+            arguments,
+        }))
+    }
+
+    /// Parses the rest of an interpolated string whose first
+    /// `Interpolation` token has been consumed. The string pieces and the
+    /// interpolated expressions become alternating parts.
+    fn interpolation(&mut self) -> Result<Expr, LoxError> {
+        let mut parts = Vec::new();
+        loop {
+            parts.push(Expr::from(Literal {
+                value: self.previous().literal.clone().unwrap(),
+            }));
+            parts.push(self.expression()?);
+            if !self.match_token_type(&[TokenType::InterpolationMiddle]) {
+                break;
+            }
+        }
+        let end = self.consume(
+            TokenType::InterpolationEnd,
+            "Expect '}' after interpolated expression.",
+        )?;
+        parts.push(Expr::from(Literal {
+            value: end.literal.clone().unwrap(),
+        }));
+        Ok(Expr::from(Interpolation { parts }))
+    }
+
+    fn primary(&mut self) -> Result<Expr, LoxError> {
+        if self.match_token_type(&[TokenType::False]) {
+            return Ok(Expr::from(Literal {
+                value: Object::False,
+            }));
+        }
+        if self.match_token_type(&[TokenType::True]) {
+            return Ok(Expr::from(Literal {
+                value: Object::True,
+            }));
+        }
+        if self.match_token_type(&[TokenType::Nil]) {
+            return Ok(Expr::from(Literal { value: Object::Nil }));
+        }
+        if self.match_token_type(&[TokenType::Number, TokenType::String]) {
+            return Ok(Expr::from(Literal {
+                value: self.previous().literal.clone().unwrap(),
+            }));
+        }
+        if self.match_token_type(&[TokenType::Interpolation]) {
+            return self.interpolation();
+        }
+        if self.match_token_type(&[TokenType::Error]) {
+            // Stands in for the bad lexeme; the program won't run anyway.
+            return Ok(Expr::from(Literal { value: Object::Nil }));
+        }
+        if self.match_token_type(&[TokenType::Super]) {
+            let keyword = self.previous().to_static();
+            self.consume(TokenType::Dot, "Expect '.' after 'super'.")?;
+            let method = self
+                .consume(TokenType::Identifier, "Expect superclass method name.")?
+                .to_static();
+            return Ok(Expr::from(Super {
+                keyword,
+                method,
+                depth: Cell::new(None),
+            }));
+        }
+        if self.match_token_type(&[TokenType::This]) {
+            return Ok(Expr::from(This {
+                keyword: self.previous().to_static(),
+                depth: Cell::new(None),
+            }));
+        }
+        if self.match_token_type(&[TokenType::Identifier]) {
+            return Ok(Expr::from(Variable {
+                name: self.previous().to_static(),
+                depth: Cell::new(None),
+            }));
+        }
+        if self.match_token_type(&[TokenType::LeftParen]) {
+            let open = self.previous.span;
+            let expr = self.expression()?;
+            self.consume_closing(TokenType::RightParen, open, "Expect ')' after expression.")?;
+            return Ok(Expr::from(Grouping {
+                expression: Box::new(expr),
+            }));
+        }
+
+        let mut at = self.peek().clone();
+        if matches!(
+            at.token_type,
+            TokenType::InterpolationMiddle | TokenType::InterpolationEnd
+        ) {
+            // An empty `${}`: point at its `}` rather than the string after.
+            at.lexeme = Cow::Borrowed("}");
+            at.span = Span::new(at.span.start, at.span.start + 1);
+        }
+        Err(LoxError::parse(
+            ErrorCode::ExpectedExpression,
+            &at,
+            "Expect expression.".to_string(),
+        ))
+    }
+}
+
+#[test]
+fn test_parser() {
+    use crate::printer::AstPrinter;
+    use crate::scanner::Scanner;
+
+    let statements = Parser::new(Scanner::new("-1 + 2 * (3 - 4) >= 5 == true;"))
+        .parse()
+        .unwrap();
+    assert_eq!(
+        AstPrinter.print_stmt(&statements[0]).unwrap(),
+        "(; (== (>= (+ (- 1) (* 2 (group (- 3 4)))) 5) true))"
+    );
+
+    assert!(Parser::new(Scanner::new("(1 + 2;")).parse().is_err());
+}
+
+#[test]
+fn test_parser_declarations() {
+    use crate::printer::AstPrinter;
+    use crate::scanner::Scanner;
+
+    let source = "var a = 1; for (var i = 0; i < 3; i = i + 1) print a.b(i) or nil;";
+    let statements = Parser::new(Scanner::new(source)).parse().unwrap();
+    assert_eq!(statements.len(), 2);
+    assert_eq!(
+        AstPrinter.print_stmt(&statements[1]).unwrap(),
+        "(block (var i 0) (while (< i 3) (block (print (or (call (. a b) i) nil)) (; (= i (+ i 1))))))"
+    );
+}
+
+#[test]
+fn test_parser_reports_every_error() {
+    use crate::scanner::Scanner;
+
+    let errors = Parser::new(Scanner::new("(1 + ;\nprint 2 * 3;\n4 - ) ;\n1 = 2;"))
+        .parse()
+        .unwrap_err();
+    assert_eq!(errors.len(), 3);
+}
+
+#[test]
+fn test_parser_reports_lexical_errors() {
+    use crate::scanner::Scanner;
+
+    // Tokens are pulled as the parser goes, so the scanner's errors come
+    // out in source order with the parser's.
+    let errors = Parser::new(Scanner::new("print 1 +;\nvar s = \"open"))
+        .parse()
+        .unwrap_err();
+    let kinds = errors
+        .iter()
+        .map(|err| (err.code(), err.diagnostic().line))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        vec![
+            (ErrorCode::ExpectedExpression, 1),
+            (ErrorCode::UnterminatedString, 2)
+        ]
+    );
+
+    // A bad lexeme is parsed as a placeholder, and doesn't cause syntax
+    // errors of its own.
+    for (source, code) in [
+        ("var x = 0x;", ErrorCode::MalformedNumber),
+        ("print \"bad \\q\";", ErrorCode::InvalidEscape),
+        ("print \"abc;", ErrorCode::UnterminatedString),
+        ("var x = 1 @ 2;", ErrorCode::UnexpectedCharacter),
+        ("print \"a${b", ErrorCode::UnterminatedInterpolation),
+    ] {
+        let errors = Parser::new(Scanner::new(source)).parse().unwrap_err();
+        let codes = errors.iter().map(LoxError::code).collect::<Vec<_>>();
+        assert_eq!(codes, vec![code], "{}", source);
+    }
+}
+
+#[test]
+fn test_parser_interpolation() {
+    use crate::printer::AstPrinter;
+    use crate::scanner::Scanner;
+
+    let statements = Parser::new(Scanner::new(r#"print "a${b}c${"d${e}"}f";"#))
+        .parse()
+        .unwrap();
+    assert_eq!(
+        AstPrinter.print_stmt(&statements[0]).unwrap(),
+        r#"(print (str "a" b "c" (str "d" e "") "f"))"#
+    );
+
+    // An empty interpolation is an error at its `}`, and the rest of the
+    // string can't be taken for an expression.
+    for (source, span) in [
+        (r#"print "a${}b" "c";"#, Span::new(10, 11)),
+        (r#"print "${}";"#, Span::new(9, 10)),
+        (r#"print "${1}${}";"#, Span::new(13, 14)),
+    ] {
+        let errors = Parser::new(Scanner::new(source)).parse().unwrap_err();
+        let errors = errors
+            .iter()
+            .map(|err| (err.code(), err.span()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            vec![(ErrorCode::ExpectedExpression, Some(span))],
+            "{}",
+            source
+        );
+    }
+}
+
+#[test]
+fn test_parser_doc_comments() {
+    use crate::scanner::Scanner;
+
+    let source = "
+        /// A point.
+        /// In the plane.
+        class Point {
+            /** Makes one. */
+            init(x) { this.x = x; }
+            plain() {}
+        }
+        /// The origin.
+        var origin = Point(/** ignored */ 0);
+        /// Not a declaration.
+        print origin;
+        fun undocumented() {}
+    ";
+    let statements = Parser::new(Scanner::new(source)).parse().unwrap();
+    assert_eq!(statements.len(), 4);
+    match &statements[0] {
+        Stmt::Class(class) => {
+            assert_eq!(class.doc.as_deref(), Some("A point.\nIn the plane."));
+            assert_eq!(class.methods[0].doc.as_deref(), Some("Makes one."));
+            assert_eq!(class.methods[1].doc, None);
+        }
+        stmt => panic!("expected a class, got {:?}", stmt),
+    }
+    match &statements[1] {
+        Stmt::Var(var) => assert_eq!(var.doc.as_deref(), Some("The origin.")),
+        stmt => panic!("expected a variable, got {:?}", stmt),
+    }
+    match &statements[3] {
+        Stmt::Function(function) => assert_eq!(function.doc, None),
+        stmt => panic!("expected a function, got {:?}", stmt),
+    }
+}
+
+#[test]
+fn test_parser_unclosed_delimiters() {
+    use crate::scanner::Scanner;
+
+    let errors = Parser::new(Scanner::new("fun f(a {}\n{ print (1;"))
+        .parse()
+        .unwrap_err();
+    let labels = errors
+        .iter()
+        .map(|err| err.diagnostic().labels[0].span)
+        .collect::<Vec<_>>();
+    assert_eq!(labels, vec![Span::new(5, 6), Span::new(19, 20)]);
+}
+
+#[test]
+fn test_parser_misspelled_keywords() {
+    use crate::scanner::Scanner;
+
+    let errors = ["fucn f() {}", "whiel (true) {}", "count 1;", "retrun;"]
+        .iter()
+        .map(|source| {
+            Parser::new(Scanner::new(source))
+                .parse()
+                .unwrap_err()
+                .remove(0)
+        })
+        .collect::<Vec<_>>();
+    let help = errors
+        .iter()
+        .map(|err| err.diagnostic().help.as_deref())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        help,
+        vec![
+            Some("Did you mean the keyword 'fun'?"),
+            Some("Did you mean the keyword 'while'?"),
+            None,
+            Some("Did you mean the keyword 'return'?"),
+        ]
+    );
+    assert_eq!(errors[0].diagnostic().labels[0].span, Span::new(0, 4));
+    assert_eq!(errors[3].code(), ErrorCode::MisspelledKeyword);
+
+    // A lone name is still a statement when it isn't close to a keyword,
+    // or too short to tell.
+    for source in ["var count = 1; count;", "var i = 1; i;"] {
+        assert!(
+            Parser::new(Scanner::new(source)).parse().is_ok(),
+            "{}",
+            source
+        );
+    }
+}
+
+#[test]
+fn test_parser_nesting_limit() {
+    use crate::scanner::Scanner;
+
+    fn parse(source: String) -> Result<Vec<Stmt>, Vec<LoxError>> {
+        Parser::new(Scanner::new(&source)).parse()
+    }
+
+    // Nesting up to the limit takes more than a test thread's stack in a
+    // debug build, so run on one as big as `Lox::main` gives itself.
+    let result = std::thread::Builder::new()
+        .stack_size(64 << 20)
+        .spawn(|| {
+            let deep = 200_000;
+            let sources = [
+                format!("print {}1;", "-".repeat(deep)),
+                format!("print {}1{};", "(".repeat(deep), ")".repeat(deep)),
+                format!("print 1{};", " + 1".repeat(deep)),
+                format!("var a; a{};", " = a".repeat(deep)),
+                format!("{}{}", "{".repeat(deep), "}".repeat(deep)),
+                format!("{}print 1;", "if (true) ".repeat(deep)),
+            ];
+            for source in sources {
+                let codes = parse(source)
+                    .unwrap_err()
+                    .iter()
+                    .map(LoxError::code)
+                    .collect::<Vec<_>>();
+                assert_eq!(codes, vec![ErrorCode::NestedTooDeeply]);
+            }
+
+            // The printed expression is one level, each operator another.
+            assert!(parse(format!("print {}1;", "-".repeat(MAX_DEPTH - 1))).is_ok());
+            assert!(parse(format!("print {}1;", "-".repeat(MAX_DEPTH))).is_err());
+            let parens = MAX_DEPTH - 1;
+            assert!(parse(format!(
+                "print {}1{};",
+                "(".repeat(parens),
+                ")".repeat(parens)
+            ))
+            .is_ok());
+            assert!(parse(format!(
+                "{}{}",
+                "{".repeat(MAX_DEPTH),
+                "}".repeat(MAX_DEPTH)
+            ))
+            .is_ok());
+        })
+        .unwrap()
+        .join();
+    assert!(result.is_ok());
+}
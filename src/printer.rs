@@ -2,32 +2,49 @@
 use crate::error::*;
 use crate::expr::*;
 use crate::literal::*;
+use crate::stmt::{self, Stmt};
 
-struct AstPrinter;
+pub struct AstPrinter;
 
 impl AstPrinter {
-    fn print(&self, expr: Box<dyn Expr>) -> Result<String, LoxError> {
-        expr.accept::<String>(Box::new(self))
+    pub fn print(&mut self, expr: &Expr) -> Result<String, LoxError> {
+        expr.accept(self)
     }
 
-    fn parenthesize(&self, name: &str, exprs: &[&Box<dyn Expr>]) -> Result<String, LoxError> {
+    pub fn print_stmt(&mut self, stmt: &Stmt) -> Result<String, LoxError> {
+        stmt.accept(self)
+    }
+
+    fn parenthesize(&mut self, name: &str, exprs: &[&Expr]) -> Result<String, LoxError> {
+        let mut parts = Vec::with_capacity(exprs.len());
+        for expr in exprs {
+            parts.push(expr.accept(self)?);
+        }
+        Ok(Self::join(name, &parts))
+    }
+
+    fn join(name: &str, parts: &[String]) -> String {
         let mut builder = String::new();
         builder.push('(');
         builder.push_str(name);
-        for expr in exprs {
+        for part in parts {
             builder.push(' ');
-            let s = expr.accept::<String>(Box::new(self))?;
-            builder.push_str(s.as_str());
+            builder.push_str(part.as_str());
         }
         builder.push(')');
-        Ok(builder)
+        builder
     }
 }
 
 impl Visitor for AstPrinter {
     type Res = String;
 
-    fn visit_binary(&self, expr: &Binary) -> Result<String, LoxError> {
+    fn visit_assign(&mut self, expr: &Assign) -> Result<String, LoxError> {
+        let value = expr.value.accept(self)?;
+        Ok(Self::join("=", &[expr.name.lexeme.to_string(), value]))
+    }
+
+    fn visit_binary(&mut self, expr: &Binary) -> Result<String, LoxError> {
         let Binary {
             left,
             operator,
@@ -36,22 +53,143 @@ impl Visitor for AstPrinter {
         self.parenthesize(&operator.lexeme, &[left, right])
     }
 
-    fn visit_grouping(&self, expr: &Grouping) -> Result<String, LoxError> {
+    fn visit_call(&mut self, expr: &Call) -> Result<String, LoxError> {
+        let mut parts = vec![expr.callee.accept(self)?];
+        for argument in expr.arguments.iter() {
+            parts.push(argument.accept(self)?);
+        }
+        Ok(Self::join("call", &parts))
+    }
+
+    fn visit_get(&mut self, expr: &Get) -> Result<String, LoxError> {
+        let object = expr.object.accept(self)?;
+        Ok(Self::join(".", &[object, expr.name.lexeme.to_string()]))
+    }
+
+    fn visit_grouping(&mut self, expr: &Grouping) -> Result<String, LoxError> {
         let Grouping { expression } = expr;
         self.parenthesize("group", &[expression])
     }
 
-    fn visit_literal(&self, expr: &Literal) -> Result<String, LoxError> {
+    fn visit_interpolation(&mut self, expr: &Interpolation) -> Result<String, LoxError> {
+        let parts = expr.parts.iter().collect::<Vec<_>>();
+        self.parenthesize("str", &parts)
+    }
+
+    fn visit_literal(&mut self, expr: &Literal) -> Result<String, LoxError> {
         if expr.value.eq(&Object::Nil) {
             return Ok(String::from("nil"));
         }
         Ok(expr.value.to_string())
     }
 
-    fn visit_unary(&self, expr: &Unary) -> Result<String, LoxError> {
+    fn visit_logical(&mut self, expr: &Logical) -> Result<String, LoxError> {
+        let Logical {
+            left,
+            operator,
+            right,
+        } = expr;
+        self.parenthesize(&operator.lexeme, &[left, right])
+    }
+
+    fn visit_set(&mut self, expr: &Set) -> Result<String, LoxError> {
+        let object = expr.object.accept(self)?;
+        let value = expr.value.accept(self)?;
+        Ok(Self::join(
+            "=",
+            &[object, expr.name.lexeme.to_string(), value],
+        ))
+    }
+
+    fn visit_super(&mut self, expr: &Super) -> Result<String, LoxError> {
+        Ok(format!("(super {})", expr.method.lexeme))
+    }
+
+    fn visit_this(&mut self, _expr: &This) -> Result<String, LoxError> {
+        Ok(String::from("this"))
+    }
+
+    fn visit_unary(&mut self, expr: &Unary) -> Result<String, LoxError> {
         let Unary { operator, right } = expr;
         self.parenthesize(&operator.lexeme, &[right])
     }
+
+    fn visit_variable(&mut self, expr: &Variable) -> Result<String, LoxError> {
+        Ok(expr.name.lexeme.to_string())
+    }
+}
+
+impl stmt::Visitor for AstPrinter {
+    type Res = String;
+
+    fn visit_block(&mut self, stmt: &stmt::Block) -> Result<String, LoxError> {
+        let mut parts = Vec::with_capacity(stmt.statements.len());
+        for statement in stmt.statements.iter() {
+            parts.push(statement.accept(self)?);
+        }
+        Ok(Self::join("block", &parts))
+    }
+
+    fn visit_class(&mut self, stmt: &stmt::Class) -> Result<String, LoxError> {
+        let mut parts = vec![stmt.name.lexeme.to_string()];
+        if let Some(superclass) = &stmt.superclass {
+            parts.push(format!("< {}", superclass.name.lexeme));
+        }
+        for method in stmt.methods.iter() {
+            parts.push(self.visit_function(method)?);
+        }
+        Ok(Self::join("class", &parts))
+    }
+
+    fn visit_expression(&mut self, stmt: &stmt::Expression) -> Result<String, LoxError> {
+        self.parenthesize(";", &[&stmt.expression])
+    }
+
+    fn visit_function(&mut self, stmt: &stmt::Function) -> Result<String, LoxError> {
+        let params = stmt
+            .params
+            .iter()
+            .map(|param| param.lexeme.as_ref())
+            .collect::<Vec<_>>();
+        let mut parts = vec![format!("{}({})", stmt.name.lexeme, params.join(" "))];
+        for statement in stmt.body.iter() {
+            parts.push(statement.accept(self)?);
+        }
+        Ok(Self::join("fun", &parts))
+    }
+
+    fn visit_if(&mut self, stmt: &stmt::If) -> Result<String, LoxError> {
+        let mut parts = vec![stmt.condition.accept(self)?, stmt.then_branch.accept(self)?];
+        if let Some(else_branch) = &stmt.else_branch {
+            parts.push(else_branch.accept(self)?);
+        }
+        Ok(Self::join("if", &parts))
+    }
+
+    fn visit_print(&mut self, stmt: &stmt::Print) -> Result<String, LoxError> {
+        self.parenthesize("print", &[&stmt.expression])
+    }
+
+    fn visit_return(&mut self, stmt: &stmt::Return) -> Result<String, LoxError> {
+        match &stmt.value {
+            Some(value) => self.parenthesize("return", &[value]),
+            None => Ok(String::from("(return)")),
+        }
+    }
+
+    fn visit_var(&mut self, stmt: &stmt::Var) -> Result<String, LoxError> {
+        let mut parts = vec![stmt.name.lexeme.to_string()];
+        if let Some(initializer) = &stmt.initializer {
+            parts.push(initializer.accept(self)?);
+        }
+        Ok(Self::join("var", &parts))
+    }
+
+    fn visit_while(&mut self, stmt: &stmt::While) -> Result<String, LoxError> {
+        let condition = stmt.condition.accept(self)?;
+        let body = stmt.body.accept(self)?;
+        Ok(Self::join("while", &[condition, body]))
+    }
 }
 
 #[test]
@@ -59,23 +197,22 @@ fn test_printer() {
     use crate::literal::*;
     use crate::token::*;
 
-    let expression: Binary = Binary {
-        left: Box::new(Unary {
+    let expression = Expr::from(Binary {
+        left: Box::new(Expr::from(Unary {
             operator: Token::new(TokenType::Minus, String::from("-"), None, 1),
-            right: Box::new(Literal {
+            right: Box::new(Expr::from(Literal {
                 value: Object::Num(123.),
-            }),
-        }),
+            })),
+        })),
         operator: Token::new(TokenType::Star, String::from("*"), None, 1),
-        right: Box::new(Grouping {
-            expression: Box::new(Literal {
+        right: Box::new(Expr::from(Grouping {
+            expression: Box::new(Expr::from(Literal {
                 value: Object::Num(45.67),
-            }),
-        }),
-    };
-    if let Ok(res) = AstPrinter.print(Box::new(expression)) {
-        println!("{}", res);
-    } else {
-        eprintln!("Failed to rloxed");
-    }
+            })),
+        })),
+    });
+    assert_eq!(
+        AstPrinter.print(&expression).unwrap(),
+        "(* (- 123) (group 45.67))"
+    );
 }
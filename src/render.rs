@@ -0,0 +1,541 @@
+//! Draws errors the way rustc does: a header with the code, the file and
+//! position, the source lines involved with the span underlined, labels
+//! for secondary spans, then notes and help.
+
+use std::env;
+use std::io::{self, IsTerminal};
+
+use crate::error::{Diagnostic, Frame, Severity};
+use crate::json::Json;
+use crate::token::Span;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+
+/// Width a tab is expanded to in snippets, so the underline lines up.
+const TAB_WIDTH: usize = 4;
+
+/// Runs of more frames of one function than this are collapsed in stack
+/// traces, keeping half as many at either end.
+const MAX_REPEATED_FRAMES: usize = 10;
+
+/// Whether reports written to stderr should be colored: only when it is a
+/// terminal, and `NO_COLOR` isn't set.
+pub fn stderr_color() -> bool {
+    io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none()
+}
+
+/// How errors are written: for people, or one JSON object per line for
+/// tools, selected with `--error-format`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ErrorFormat {
+    #[default]
+    Human,
+    Json,
+}
+
+impl ErrorFormat {
+    pub fn from_name(name: &str) -> Option<ErrorFormat> {
+        match name {
+            "human" => Some(ErrorFormat::Human),
+            "json" => Some(ErrorFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// One underlined span of a snippet.
+struct Annotation<'a> {
+    start: Position,
+    end: Position,
+    message: Option<&'a str>,
+    primary: bool,
+}
+
+#[derive(Clone, Copy)]
+struct Position {
+    /// 0-based line.
+    line: usize,
+    /// Byte offset within the line.
+    offset: usize,
+}
+
+pub struct Renderer<'a> {
+    file: &'a str,
+    /// The source the spans point into. Without it only the header and
+    /// location are drawn.
+    source: Option<&'a str>,
+    color: bool,
+    format: ErrorFormat,
+}
+
+impl<'a> Renderer<'a> {
+    pub fn new(file: &'a str, source: Option<&'a str>) -> Renderer<'a> {
+        Renderer {
+            file,
+            source,
+            color: false,
+            format: ErrorFormat::Human,
+        }
+    }
+
+    pub fn color(self, color: bool) -> Renderer<'a> {
+        Renderer { color, ..self }
+    }
+
+    pub fn format(self, format: ErrorFormat) -> Renderer<'a> {
+        Renderer { format, ..self }
+    }
+
+    /// The report for `diagnostic`, ending in a newline; human reports
+    /// end in a blank line.
+    pub fn render(&self, diagnostic: &Diagnostic) -> String {
+        match self.format {
+            ErrorFormat::Human => self.human(diagnostic),
+            ErrorFormat::Json => format!("{}\n", self.json(diagnostic)),
+        }
+    }
+
+    fn json(&self, diagnostic: &Diagnostic) -> Json {
+        let span = |span: Span| {
+            Json::Object(vec![
+                ("start", Json::from(span.start)),
+                ("end", Json::from(span.end)),
+            ])
+        };
+        let severity = match diagnostic.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        let labels = diagnostic
+            .labels
+            .iter()
+            .map(|label| {
+                Json::Object(vec![
+                    ("span", span(label.span)),
+                    ("message", Json::from(label.message.as_str())),
+                ])
+            })
+            .collect();
+        let notes = diagnostic
+            .notes
+            .iter()
+            .map(|note| Json::from(note.as_str()))
+            .collect();
+        let trace = diagnostic
+            .trace
+            .iter()
+            .map(|frame| {
+                Json::Object(vec![
+                    ("function", Json::from(frame.function.as_str())),
+                    ("file", Json::from(self.file)),
+                    ("line", Json::from(frame.line)),
+                ])
+            })
+            .collect();
+        Json::Object(vec![
+            ("code", Json::from(diagnostic.code.as_str())),
+            ("severity", Json::from(severity)),
+            ("message", Json::from(diagnostic.message.as_str())),
+            ("file", Json::from(self.file)),
+            ("line", Json::from(diagnostic.line)),
+            ("column", Json::from(diagnostic.column)),
+            ("span", diagnostic.span.map_or(Json::Null, span)),
+            ("labels", Json::Array(labels)),
+            ("notes", Json::Array(notes)),
+            ("help", Json::from(diagnostic.help.as_deref())),
+            ("trace", Json::Array(trace)),
+        ])
+    }
+
+    fn human(&self, diagnostic: &Diagnostic) -> String {
+        let (severity, color) = match diagnostic.severity {
+            Severity::Error => ("error", RED),
+            Severity::Warning => ("warning", YELLOW),
+        };
+
+        let mut annotations = Vec::new();
+        if let Some(source) = self.source {
+            if let Some(span) = diagnostic.span {
+                annotations.extend(Renderer::annotate(source, span, None, true));
+            }
+            for label in diagnostic.labels.iter() {
+                annotations.extend(Renderer::annotate(
+                    source,
+                    label.span,
+                    Some(&label.message),
+                    false,
+                ));
+            }
+        }
+
+        // Underlines sharing a line are drawn left to right.
+        annotations.sort_by_key(|annotation| (annotation.start.line, annotation.start.offset));
+
+        // The lines to show: where each annotation starts and ends, or
+        // the error's line when it has no usable span.
+        let mut lines = annotations
+            .iter()
+            .flat_map(|annotation| [annotation.start.line, annotation.end.line])
+            .collect::<Vec<_>>();
+        if annotations.iter().all(|annotation| !annotation.primary) && self.source.is_some() {
+            lines.push(diagnostic.line.saturating_sub(1));
+        }
+        lines.sort_unstable();
+        lines.dedup();
+        let source_lines = self
+            .source
+            .map(|source| source.split('\n').collect::<Vec<_>>())
+            .unwrap_or_default();
+        lines.retain(|&line| line < source_lines.len());
+
+        let gutter = lines
+            .last()
+            .map_or(diagnostic.line, |line| line + 1)
+            .to_string()
+            .len();
+        let pad = " ".repeat(gutter);
+
+        let mut out = String::new();
+        out.push_str(&format!(
+            "{}{}[{}]{}{}: {}{}\n",
+            self.paint(color),
+            severity,
+            diagnostic.code,
+            self.paint(RESET),
+            self.paint(BOLD),
+            diagnostic.message,
+            self.paint(RESET)
+        ));
+        let position = match diagnostic.column {
+            Some(column) => format!("{}:{}", diagnostic.line, column),
+            None => diagnostic.line.to_string(),
+        };
+        out.push_str(&format!(
+            "{}{}-->{} {}:{}\n",
+            pad,
+            self.paint(BLUE),
+            self.paint(RESET),
+            self.file,
+            position
+        ));
+
+        if !lines.is_empty() {
+            out.push_str(&self.gutter(&pad, ""));
+            let mut previous = None;
+            for &line in lines.iter() {
+                if previous.is_some_and(|previous| line > previous + 1) {
+                    out.push_str(&format!(
+                        "{}{}...{}\n",
+                        pad,
+                        self.paint(BLUE),
+                        self.paint(RESET)
+                    ));
+                }
+                previous = Some(line);
+
+                let text = source_lines[line].trim_end_matches('\r');
+                let number = format!("{:>width$}", line + 1, width = gutter);
+                out.push_str(&self.gutter(&number, &expand_tabs(text)));
+
+                for annotation in annotations.iter() {
+                    if line < annotation.start.line || line > annotation.end.line {
+                        continue;
+                    }
+                    // Only the first and last line of a span are shown,
+                    // so underline to the end of the first one.
+                    let from = if line == annotation.start.line {
+                        annotation.start.offset
+                    } else {
+                        0
+                    };
+                    let to = if line == annotation.end.line {
+                        annotation.end.offset
+                    } else {
+                        text.len()
+                    };
+                    let (from, to) = (from.min(text.len()), to.min(text.len()));
+                    let indent = display_width(&text[..from]);
+                    let width = display_width(&text[from..to.max(from)]).max(1);
+                    let (marker, color) = if annotation.primary {
+                        ('^', color)
+                    } else {
+                        ('-', BLUE)
+                    };
+                    let mut underline = format!(
+                        "{}{}{}",
+                        " ".repeat(indent),
+                        self.paint(color),
+                        marker.to_string().repeat(width)
+                    );
+                    if let Some(message) = annotation.message {
+                        if line == annotation.end.line {
+                            underline.push(' ');
+                            underline.push_str(message);
+                        }
+                    }
+                    underline.push_str(self.paint(RESET));
+                    out.push_str(&self.gutter(&pad, &underline));
+                }
+            }
+        }
+
+        for note in diagnostic.notes.iter() {
+            out.push_str(&format!(
+                "{} {}={} {}note{}: {}\n",
+                pad,
+                self.paint(BLUE),
+                self.paint(RESET),
+                self.paint(BOLD),
+                self.paint(RESET),
+                note
+            ));
+        }
+        if let Some(help) = &diagnostic.help {
+            out.push_str(&format!(
+                "{} {}={} {}help{}: {}\n",
+                pad,
+                self.paint(BLUE),
+                self.paint(RESET),
+                self.paint(BOLD),
+                self.paint(RESET),
+                help
+            ));
+        }
+        if !diagnostic.trace.is_empty() {
+            out.push_str(&self.trace(&diagnostic.trace));
+        }
+        // A blank line between reports, as rustc does.
+        out.push('\n');
+        out
+    }
+
+    /// Lists the frames innermost first, collapsing long runs of one
+    /// function such as deep recursion.
+    fn trace(&self, trace: &[Frame]) -> String {
+        let width = (trace.len() - 1).to_string().len();
+        let mut out = format!(
+            "{}stack backtrace, most recent call first:{}\n",
+            self.paint(BOLD),
+            self.paint(RESET)
+        );
+        let frame = |index: usize| {
+            format!(
+                "  {}{:>width$}:{} {}, called at {}:{}\n",
+                self.paint(BLUE),
+                index,
+                self.paint(RESET),
+                trace[index].function,
+                self.file,
+                trace[index].line,
+                width = width
+            )
+        };
+        let mut start = 0;
+        while start < trace.len() {
+            let function = &trace[start].function;
+            let run = trace[start..]
+                .iter()
+                .take_while(|frame| frame.function == *function)
+                .count();
+            let end = start + run;
+            if run > MAX_REPEATED_FRAMES {
+                let kept = MAX_REPEATED_FRAMES / 2;
+                (start..start + kept).for_each(|index| out.push_str(&frame(index)));
+                out.push_str(&format!(
+                    "  {:width$}  ... {} more frames of {}\n",
+                    "",
+                    run - 2 * kept,
+                    function,
+                    width = width
+                ));
+                (end - kept..end).for_each(|index| out.push_str(&frame(index)));
+            } else {
+                (start..end).for_each(|index| out.push_str(&frame(index)));
+            }
+            start = end;
+        }
+        out
+    }
+
+    fn gutter(&self, left: &str, text: &str) -> String {
+        let line = format!(
+            "{}{} |{} {}",
+            self.paint(BLUE),
+            left,
+            self.paint(RESET),
+            text
+        );
+        format!("{}\n", line.trim_end())
+    }
+
+    fn paint(&self, code: &'static str) -> &'static str {
+        if self.color {
+            code
+        } else {
+            ""
+        }
+    }
+
+    /// Locates `span` in `source`. Spans that don't fit the source, such
+    /// as those of a function declared in an earlier REPL line, are
+    /// dropped rather than drawn in the wrong place.
+    fn annotate<'m>(
+        source: &str,
+        span: Span,
+        message: Option<&'m str>,
+        primary: bool,
+    ) -> Option<Annotation<'m>> {
+        if span.start > span.end
+            || span.end > source.len()
+            || !source.is_char_boundary(span.start)
+            || !source.is_char_boundary(span.end)
+        {
+            return None;
+        }
+        let position = |offset: usize| {
+            let before = &source[..offset];
+            let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+            Position {
+                line: before.matches('\n').count(),
+                offset: offset - line_start,
+            }
+        };
+        // Keep a trailing newline out of the span so it ends on its line.
+        let end = if span.end > span.start && source[..span.end].ends_with('\n') {
+            span.end - 1
+        } else {
+            span.end
+        };
+        Some(Annotation {
+            start: position(span.start),
+            end: position(end),
+            message,
+            primary,
+        })
+    }
+}
+
+fn expand_tabs(text: &str) -> String {
+    text.replace('\t', &" ".repeat(TAB_WIDTH))
+}
+
+fn display_width(text: &str) -> usize {
+    text.chars()
+        .map(|ch| if ch == '\t' { TAB_WIDTH } else { 1 })
+        .sum()
+}
+
+#[test]
+fn test_render() {
+    use crate::error::ErrorCode;
+    use crate::scanner::Scanner;
+
+    let source = "var a = 1;\nprint \"open\n";
+    let (_, errors) = Scanner::new(source).scan_tokens();
+    let report = Renderer::new("main.lox", Some(source)).render(errors[0].diagnostic());
+    assert_eq!(
+        report,
+        "\
+error[E0102]: Unterminated string.
+ --> main.lox:2:7
+  |
+2 | print \"open
+  |       ^^^^^
+
+"
+    );
+
+    let source = "fun f() {\n\tprint (1 +\n}";
+    let diagnostic = Diagnostic::new(
+        ErrorCode::UnexpectedCharacter,
+        3,
+        1,
+        Span::new(22, 23),
+        "Unexpected character: }".to_string(),
+    )
+    .with_label(Span::new(8, 9), "unclosed delimiter")
+    .with_label(Span::new(17, 18), "this '(' too")
+    .with_note("blocks need a closing '}'.")
+    .with_help("add the missing ')'.");
+    let report = Renderer::new("f.lox", Some(source)).render(&diagnostic);
+    assert_eq!(
+        report,
+        "\
+error[E0101]: Unexpected character: }
+ --> f.lox:3:1
+  |
+1 | fun f() {
+  |         - unclosed delimiter
+2 |     print (1 +
+  |           - this '(' too
+3 | }
+  | ^
+  = note: blocks need a closing '}'.
+  = help: add the missing ')'.
+
+"
+    );
+
+    // Without the source there is no snippet, and color is opt-in.
+    let report = Renderer::new("f.lox", None).color(true).render(&diagnostic);
+    assert!(report.starts_with("\x1b[1;31merror[E0101]\x1b[0m\x1b[1m: Unexpected"));
+    assert!(!report.contains(" | "));
+
+    // JSON is never colored and fits on one line.
+    let report = Renderer::new("f.lox", Some(source))
+        .color(true)
+        .format(ErrorFormat::Json)
+        .render(&diagnostic);
+    assert_eq!(
+        report,
+        r#"{"code":"E0101","severity":"error","message":"Unexpected character: }","file":"f.lox","line":3,"column":1,"span":{"start":22,"end":23},"labels":[{"span":{"start":8,"end":9},"message":"unclosed delimiter"},{"span":{"start":17,"end":18},"message":"this '(' too"}],"notes":["blocks need a closing '}'."],"help":"add the missing ')'.","trace":[]}"#
+            .to_string()
+            + "\n"
+    );
+}
+
+#[test]
+fn test_render_trace() {
+    use crate::error::{ErrorCode, LoxError};
+    use crate::token::{Token, TokenType};
+
+    let call = Token::new(TokenType::RightParen, ")".to_string(), None, 4);
+    let mut error = LoxError::runtime(
+        ErrorCode::StackOverflow,
+        &Token::new(TokenType::RightParen, ")".to_string(), None, 2),
+        "Stack overflow.".to_string(),
+    );
+    for _ in 0..12 {
+        error = error.with_frame("fib".to_string(), &call);
+    }
+    let error = error
+        .with_frame("Math.fib".to_string(), &call)
+        .with_frame("main".to_string(), &call);
+    let report = Renderer::new("fib.lox", None).render(error.diagnostic());
+    assert_eq!(
+        report,
+        "\
+error[E0409]: Stack overflow.
+ --> fib.lox:2
+stack backtrace, most recent call first:
+   0: fib, called at fib.lox:4
+   1: fib, called at fib.lox:4
+   2: fib, called at fib.lox:4
+   3: fib, called at fib.lox:4
+   4: fib, called at fib.lox:4
+      ... 2 more frames of fib
+   7: fib, called at fib.lox:4
+   8: fib, called at fib.lox:4
+   9: fib, called at fib.lox:4
+  10: fib, called at fib.lox:4
+  11: fib, called at fib.lox:4
+  12: Math.fib, called at fib.lox:4
+  13: main, called at fib.lox:4
+
+"
+    );
+}
@@ -0,0 +1,822 @@
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+
+use crate::error::{Diagnostic, ErrorCode, LoxError};
+use crate::expr::{self, Expr};
+use crate::literal::Object;
+use crate::stmt::{self, Stmt};
+use crate::token::{Token, TokenType};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FunctionType {
+    None,
+    Function,
+    Initializer,
+    Method,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ClassType {
+    None,
+    Class,
+    Subclass,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BindingKind {
+    Variable,
+    Parameter,
+    /// Functions, classes, `this` and `super`, which are never warned
+    /// about being unused.
+    Other,
+}
+
+/// What the resolver knows about a local name.
+struct Binding {
+    /// The declaring token; `this` and `super` have none.
+    name: Option<Token<'static>>,
+    kind: BindingKind,
+    /// Whether the initializer has finished resolving.
+    defined: bool,
+    /// Whether a value has been stored, by the initializer or an
+    /// assignment earlier in the source.
+    assigned: bool,
+    used: bool,
+    /// The function declaring it, numbered in source order; 0 is
+    /// top-level code.
+    function: usize,
+}
+
+/// A variable, by name and the index of its scope in `Resolver::scopes`,
+/// or `None` for a global.
+type VariableKey = (String, Option<usize>);
+
+/// A loop being resolved. The next iteration may read what the body
+/// assigns, so reads of variables declared outside the loop that look
+/// unassigned are only reported once the whole body has been seen.
+#[derive(Default)]
+struct Loop {
+    /// `scopes.len()` when the loop started.
+    scopes: usize,
+    assigned: HashSet<VariableKey>,
+    uninitialized_reads: Vec<(VariableKey, Diagnostic)>,
+}
+
+impl Loop {
+    /// Whether the variable was declared before the loop, so that it
+    /// keeps its value from one iteration to the next.
+    fn outlives(&self, key: &VariableKey) -> bool {
+        key.1.is_none_or(|scope| scope < self.scopes)
+    }
+}
+
+/// Static pass run between parsing and interpreting. It records on every
+/// `Variable`, `Assign`, `This` and `Super` node how many scopes away its
+/// binding lives, and reports the semantic errors the parser cannot see.
+/// Names left unresolved are globals.
+///
+/// It also looks for code that is probably wrong: unused locals,
+/// unreachable code, shadowing, reads of variables not yet assigned and
+/// comparisons with `nil` that can't be true.
+pub struct Resolver {
+    /// One map per local scope.
+    scopes: Vec<HashMap<String, Binding>>,
+    /// Globals declared without an initializer and not assigned since.
+    unassigned_globals: HashSet<String>,
+    current_function: FunctionType,
+    /// The number of the function being resolved, see `Binding::function`.
+    function: usize,
+    functions: usize,
+    current_class: ClassType,
+    /// The loops being resolved, innermost last.
+    loops: Vec<Loop>,
+    errors: Vec<LoxError>,
+    warnings: Vec<Diagnostic>,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Resolver {
+            scopes: Vec::new(),
+            unassigned_globals: HashSet::new(),
+            current_function: FunctionType::None,
+            function: 0,
+            functions: 0,
+            current_class: ClassType::None,
+            loops: Vec::new(),
+            errors: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+}
+
+impl Resolver {
+    pub fn resolve(&mut self, statements: &[Stmt]) -> Result<(), Vec<LoxError>> {
+        self.resolve_statements(statements);
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
+    }
+
+    /// Takes the warnings found by `resolve`, in source order.
+    pub fn warnings(&mut self) -> Vec<Diagnostic> {
+        let mut warnings = std::mem::take(&mut self.warnings);
+        warnings.sort_by_key(|warning| (warning.line, warning.column));
+        warnings
+    }
+
+    fn resolve_statements(&mut self, statements: &[Stmt]) {
+        let mut returned = None;
+        let mut reported = false;
+        for (index, statement) in statements.iter().enumerate() {
+            if let (Some(keyword), false) = (returned, reported) {
+                self.unreachable(keyword, &statements[index..]);
+                reported = true;
+            }
+            self.resolve_stmt(statement);
+            returned = returned.or_else(|| returns(statement));
+        }
+    }
+
+    /// Warns about the `dead` statements after a `return`, pointing at the
+    /// first one with a position: a bare literal or an empty block has none.
+    fn unreachable(&mut self, keyword: &Token, dead: &[Stmt]) {
+        let warning = match dead.iter().find_map(first_token) {
+            Some(token) => Diagnostic::at_token(
+                ErrorCode::UnreachableCode,
+                token,
+                "Unreachable code.".to_string(),
+            )
+            .with_label(keyword.span, "any code after this 'return' is unreachable"),
+            None => Diagnostic::at_token(
+                ErrorCode::UnreachableCode,
+                keyword,
+                "Code after this 'return' is unreachable.".to_string(),
+            ),
+        };
+        self.warn(warning);
+    }
+
+    fn resolve_stmt(&mut self, stmt: &Stmt) {
+        // The visitor never fails: errors are collected in `self.errors`.
+        let _ = stmt.accept(self);
+    }
+
+    fn resolve_expr(&mut self, expr: &Expr) {
+        let _ = expr.accept(self);
+    }
+
+    fn resolve_function(&mut self, function: &stmt::Function, function_type: FunctionType) {
+        let enclosing_function = (self.current_function, self.function);
+        self.current_function = function_type;
+        self.functions += 1;
+        self.function = self.functions;
+
+        self.begin_scope();
+        for param in function.params.iter() {
+            self.declare(param, BindingKind::Parameter);
+            self.define(param);
+        }
+        self.resolve_statements(&function.body);
+        self.end_scope();
+
+        (self.current_function, self.function) = enclosing_function;
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        let Some(scope) = self.scopes.pop() else {
+            return;
+        };
+        for binding in scope.into_values() {
+            let Some(name) = binding.name.filter(|_| !binding.used) else {
+                continue;
+            };
+            let kind = match binding.kind {
+                BindingKind::Variable => "variable",
+                BindingKind::Parameter => "parameter",
+                BindingKind::Other => continue,
+            };
+            if name.lexeme.starts_with('_') {
+                continue;
+            }
+            self.warn(
+                Diagnostic::at_token(
+                    ErrorCode::UnusedVariable,
+                    &name,
+                    format!("Unused {} '{}'.", kind, name.lexeme),
+                )
+                .with_help(format!(
+                    "If this is intentional, prefix it with an underscore: '_{}'.",
+                    name.lexeme
+                )),
+            );
+        }
+    }
+
+    fn declare(&mut self, name: &Token<'static>, kind: BindingKind) {
+        let Some(scope) = self.scopes.last() else {
+            self.unassigned_globals.remove(name.lexeme.as_ref());
+            return;
+        };
+        if scope.contains_key(name.lexeme.as_ref()) {
+            self.error(
+                ErrorCode::AlreadyDeclared,
+                name,
+                "Already a variable with this name in this scope.",
+            );
+            return;
+        }
+        let shadowed = self
+            .scopes
+            .iter()
+            .rev()
+            .skip(1)
+            .find_map(|scope| scope.get(name.lexeme.as_ref()))
+            .and_then(|binding| binding.name.as_ref())
+            .map(|outer| outer.span);
+        if let Some(outer) = shadowed {
+            self.warn(
+                Diagnostic::at_token(
+                    ErrorCode::ShadowedVariable,
+                    name,
+                    format!("'{}' shadows a declaration in an outer scope.", name.lexeme),
+                )
+                .with_label(outer, format!("'{}' is declared here", name.lexeme)),
+            );
+        }
+        let binding = Binding {
+            name: Some(name.clone()),
+            kind,
+            defined: false,
+            assigned: kind != BindingKind::Variable,
+            used: false,
+            function: self.function,
+        };
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.lexeme.to_string(), binding);
+        }
+    }
+
+    fn define(&mut self, name: &Token) {
+        if let Some(binding) = self
+            .scopes
+            .last_mut()
+            .and_then(|scope| scope.get_mut(name.lexeme.as_ref()))
+        {
+            binding.defined = true;
+        }
+    }
+
+    /// Binds `this` or `super` in the innermost scope.
+    fn define_implicit(&mut self, name: &str) {
+        let binding = Binding {
+            name: None,
+            kind: BindingKind::Other,
+            defined: true,
+            assigned: true,
+            used: true,
+            function: self.function,
+        };
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), binding);
+        }
+    }
+
+    fn resolve_local(&mut self, name: &Token, depth: &Cell<Option<usize>>) -> Option<&mut Binding> {
+        for (distance, scope) in self.scopes.iter_mut().rev().enumerate() {
+            if let Some(binding) = scope.get_mut(name.lexeme.as_ref()) {
+                depth.set(Some(distance));
+                return Some(binding);
+            }
+        }
+        None
+    }
+
+    fn error(&mut self, code: ErrorCode, token: &Token, message: &str) {
+        self.errors
+            .push(LoxError::resolve(code, token, message.to_string()));
+    }
+
+    fn warn(&mut self, warning: Diagnostic) {
+        self.warnings.push(warning);
+    }
+
+    /// The key of the variable `depth` says `name` resolved to.
+    fn variable_key(&self, name: &Token, depth: &Cell<Option<usize>>) -> VariableKey {
+        let scope = depth.get().map(|depth| self.scopes.len() - 1 - depth);
+        (name.lexeme.to_string(), scope)
+    }
+
+    /// Reports that `name` is read before it is assigned, unless a loop
+    /// around the read that the variable lives outside of may assign it
+    /// first, which is known once that loop has been resolved.
+    fn uninitialized_read(&mut self, name: &Token, depth: &Cell<Option<usize>>) {
+        let warning = Diagnostic::at_token(
+            ErrorCode::UninitializedRead,
+            name,
+            format!(
+                "'{}' is read before it is assigned, so it is nil.",
+                name.lexeme
+            ),
+        );
+        let key = self.variable_key(name, depth);
+        match self.loops.last_mut() {
+            Some(innermost) if innermost.outlives(&key) => {
+                innermost.uninitialized_reads.push((key, warning));
+            }
+            _ => self.warn(warning),
+        }
+    }
+
+    fn resolve_loop(&mut self, stmt: &stmt::While) {
+        self.loops.push(Loop {
+            scopes: self.scopes.len(),
+            ..Loop::default()
+        });
+        self.resolve_expr(&stmt.condition);
+        self.resolve_stmt(&stmt.body);
+        let finished = self.loops.pop().unwrap();
+        for (key, warning) in finished.uninitialized_reads {
+            if finished.assigned.contains(&key) {
+                continue;
+            }
+            // An enclosing loop may still assign it.
+            match self.loops.last_mut() {
+                Some(outer) if outer.outlives(&key) => {
+                    outer.uninitialized_reads.push((key, warning));
+                }
+                _ => self.warn(warning),
+            }
+        }
+        if let Some(outer) = self.loops.last_mut() {
+            outer.assigned.extend(finished.assigned);
+        }
+    }
+}
+
+/// The `return` that makes `stmt` return whichever way it runs, if any.
+fn returns(stmt: &Stmt) -> Option<&Token<'static>> {
+    match stmt {
+        Stmt::Return(stmt) => Some(&stmt.keyword),
+        Stmt::Block(block) => block.statements.iter().find_map(returns),
+        Stmt::If(stmt) => {
+            returns(stmt.else_branch.as_ref()?)?;
+            returns(&stmt.then_branch)
+        }
+        _ => None,
+    }
+}
+
+/// The token `stmt` starts at, or near: declarations are found by name.
+fn first_token(stmt: &Stmt) -> Option<&Token<'static>> {
+    match stmt {
+        Stmt::Block(block) => block.statements.iter().find_map(first_token),
+        Stmt::Class(class) => Some(&class.name),
+        Stmt::Expression(stmt) => leftmost_token(&stmt.expression),
+        Stmt::Function(function) => Some(&function.name),
+        Stmt::If(stmt) => Some(&stmt.keyword),
+        Stmt::Print(stmt) => Some(&stmt.keyword),
+        Stmt::Return(stmt) => Some(&stmt.keyword),
+        Stmt::Var(var) => Some(&var.name),
+        Stmt::While(stmt) => Some(&stmt.keyword),
+    }
+}
+
+/// The first token of `expr` in the source, if it has any: literals keep
+/// none.
+fn leftmost_token(expr: &Expr) -> Option<&Token<'static>> {
+    match expr {
+        Expr::Assign(assign) => Some(&assign.name),
+        Expr::Binary(binary) => leftmost_token(&binary.left).or(Some(&binary.operator)),
+        Expr::Call(call) => leftmost_token(&call.callee).or(Some(&call.paren)),
+        Expr::Get(get) => leftmost_token(&get.object).or(Some(&get.name)),
+        Expr::Grouping(grouping) => leftmost_token(&grouping.expression),
+        Expr::Interpolation(interpolation) => interpolation.parts.iter().find_map(leftmost_token),
+        Expr::Literal(_) => None,
+        Expr::Logical(logical) => leftmost_token(&logical.left).or(Some(&logical.operator)),
+        Expr::Set(set) => leftmost_token(&set.object).or(Some(&set.name)),
+        Expr::Super(expr) => Some(&expr.keyword),
+        Expr::This(expr) => Some(&expr.keyword),
+        Expr::Unary(unary) => Some(&unary.operator),
+        Expr::Variable(variable) => Some(&variable.name),
+    }
+}
+
+/// Whether `expr` can't evaluate to nil, judging from its form alone.
+fn never_nil(expr: &Expr) -> bool {
+    match expr {
+        Expr::Literal(literal) => !matches!(literal.value, Object::Nil),
+        Expr::Binary(_) | Expr::Interpolation(_) | Expr::Unary(_) | Expr::This(_) => true,
+        Expr::Grouping(grouping) => never_nil(&grouping.expression),
+        Expr::Logical(logical) => never_nil(&logical.left) && never_nil(&logical.right),
+        Expr::Assign(assign) => never_nil(&assign.value),
+        _ => false,
+    }
+}
+
+fn is_nil(expr: &Expr) -> bool {
+    matches!(expr, Expr::Literal(literal) if matches!(literal.value, Object::Nil))
+}
+
+impl expr::Visitor for Resolver {
+    type Res = ();
+
+    fn visit_assign(&mut self, expr: &expr::Assign) -> Result<(), LoxError> {
+        self.resolve_expr(&expr.value);
+        match self.resolve_local(&expr.name, &expr.depth) {
+            Some(binding) => binding.assigned = true,
+            None => {
+                self.unassigned_globals.remove(expr.name.lexeme.as_ref());
+            }
+        }
+        if !self.loops.is_empty() {
+            let key = self.variable_key(&expr.name, &expr.depth);
+            self.loops.last_mut().unwrap().assigned.insert(key);
+        }
+        Ok(())
+    }
+
+    fn visit_binary(&mut self, expr: &expr::Binary) -> Result<(), LoxError> {
+        self.resolve_expr(&expr.left);
+        self.resolve_expr(&expr.right);
+
+        let always = match expr.operator.token_type {
+            TokenType::EqualEqual => "false",
+            TokenType::BangEqual => "true",
+            _ => return Ok(()),
+        };
+        let other = if is_nil(&expr.right) {
+            &expr.left
+        } else if is_nil(&expr.left) {
+            &expr.right
+        } else {
+            return Ok(());
+        };
+        if never_nil(other) {
+            self.warn(
+                Diagnostic::at_token(
+                    ErrorCode::NilComparison,
+                    &expr.operator,
+                    format!("Comparison with nil is always {}.", always),
+                )
+                .with_note("The other operand can never be nil."),
+            );
+        }
+        Ok(())
+    }
+
+    fn visit_call(&mut self, expr: &expr::Call) -> Result<(), LoxError> {
+        self.resolve_expr(&expr.callee);
+        for argument in expr.arguments.iter() {
+            self.resolve_expr(argument);
+        }
+        Ok(())
+    }
+
+    fn visit_get(&mut self, expr: &expr::Get) -> Result<(), LoxError> {
+        self.resolve_expr(&expr.object);
+        Ok(())
+    }
+
+    fn visit_grouping(&mut self, expr: &expr::Grouping) -> Result<(), LoxError> {
+        self.resolve_expr(&expr.expression);
+        Ok(())
+    }
+
+    fn visit_interpolation(&mut self, expr: &expr::Interpolation) -> Result<(), LoxError> {
+        for part in expr.parts.iter() {
+            self.resolve_expr(part);
+        }
+        Ok(())
+    }
+
+    fn visit_literal(&mut self, _expr: &expr::Literal) -> Result<(), LoxError> {
+        Ok(())
+    }
+
+    fn visit_logical(&mut self, expr: &expr::Logical) -> Result<(), LoxError> {
+        self.resolve_expr(&expr.left);
+        self.resolve_expr(&expr.right);
+        Ok(())
+    }
+
+    fn visit_set(&mut self, expr: &expr::Set) -> Result<(), LoxError> {
+        self.resolve_expr(&expr.value);
+        self.resolve_expr(&expr.object);
+        Ok(())
+    }
+
+    fn visit_super(&mut self, expr: &expr::Super) -> Result<(), LoxError> {
+        match self.current_class {
+            ClassType::None => self.error(
+                ErrorCode::SuperOutsideClass,
+                &expr.keyword,
+                "Can't use 'super' outside of a class.",
+            ),
+            ClassType::Class => self.error(
+                ErrorCode::SuperWithoutSuperclass,
+                &expr.keyword,
+                "Can't use 'super' in a class with no superclass.",
+            ),
+            ClassType::Subclass => {
+                self.resolve_local(&expr.keyword, &expr.depth);
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_this(&mut self, expr: &expr::This) -> Result<(), LoxError> {
+        if self.current_class == ClassType::None {
+            self.error(
+                ErrorCode::ThisOutsideClass,
+                &expr.keyword,
+                "Can't use 'this' outside of a class.",
+            );
+            return Ok(());
+        }
+        self.resolve_local(&expr.keyword, &expr.depth);
+        Ok(())
+    }
+
+    fn visit_unary(&mut self, expr: &expr::Unary) -> Result<(), LoxError> {
+        self.resolve_expr(&expr.right);
+        Ok(())
+    }
+
+    fn visit_variable(&mut self, expr: &expr::Variable) -> Result<(), LoxError> {
+        let in_own_initializer = self
+            .scopes
+            .last()
+            .and_then(|scope| scope.get(expr.name.lexeme.as_ref()))
+            .is_some_and(|binding| !binding.defined);
+        if in_own_initializer {
+            self.error(
+                ErrorCode::ReadInOwnInitializer,
+                &expr.name,
+                "Can't read local variable in its own initializer.",
+            );
+        }
+
+        // Reads from another function may well happen after an
+        // assignment, so only those in the declaring one are checked.
+        let function = self.function;
+        let unassigned = match self.resolve_local(&expr.name, &expr.depth) {
+            Some(binding) => {
+                binding.used = true;
+                let unassigned = !binding.assigned && binding.function == function;
+                // Report each variable once.
+                binding.assigned |= unassigned;
+                unassigned
+            }
+            None => function == 0 && self.unassigned_globals.remove(expr.name.lexeme.as_ref()),
+        };
+        if unassigned && !in_own_initializer {
+            self.uninitialized_read(&expr.name, &expr.depth);
+        }
+        Ok(())
+    }
+}
+
+impl stmt::Visitor for Resolver {
+    type Res = ();
+
+    fn visit_block(&mut self, stmt: &stmt::Block) -> Result<(), LoxError> {
+        self.begin_scope();
+        self.resolve_statements(&stmt.statements);
+        self.end_scope();
+        Ok(())
+    }
+
+    fn visit_class(&mut self, stmt: &stmt::Class) -> Result<(), LoxError> {
+        let enclosing_class = self.current_class;
+        self.current_class = ClassType::Class;
+
+        self.declare(&stmt.name, BindingKind::Other);
+        self.define(&stmt.name);
+
+        if let Some(superclass) = &stmt.superclass {
+            if superclass.name.lexeme == stmt.name.lexeme {
+                self.error(
+                    ErrorCode::InheritFromSelf,
+                    &superclass.name,
+                    "A class can't inherit from itself.",
+                );
+            }
+            self.current_class = ClassType::Subclass;
+            let _ = expr::Visitor::visit_variable(self, superclass);
+
+            self.begin_scope();
+            self.define_implicit("super");
+        }
+
+        self.begin_scope();
+        self.define_implicit("this");
+
+        for method in stmt.methods.iter() {
+            let declaration = if method.name.lexeme == "init" {
+                FunctionType::Initializer
+            } else {
+                FunctionType::Method
+            };
+            self.resolve_function(method, declaration);
+        }
+
+        self.end_scope();
+        if stmt.superclass.is_some() {
+            self.end_scope();
+        }
+
+        self.current_class = enclosing_class;
+        Ok(())
+    }
+
+    fn visit_expression(&mut self, stmt: &stmt::Expression) -> Result<(), LoxError> {
+        self.resolve_expr(&stmt.expression);
+        Ok(())
+    }
+
+    fn visit_function(&mut self, stmt: &stmt::Function) -> Result<(), LoxError> {
+        self.declare(&stmt.name, BindingKind::Other);
+        self.define(&stmt.name);
+
+        self.resolve_function(stmt, FunctionType::Function);
+        Ok(())
+    }
+
+    fn visit_if(&mut self, stmt: &stmt::If) -> Result<(), LoxError> {
+        self.resolve_expr(&stmt.condition);
+        self.resolve_stmt(&stmt.then_branch);
+        if let Some(else_branch) = &stmt.else_branch {
+            self.resolve_stmt(else_branch);
+        }
+        Ok(())
+    }
+
+    fn visit_print(&mut self, stmt: &stmt::Print) -> Result<(), LoxError> {
+        self.resolve_expr(&stmt.expression);
+        Ok(())
+    }
+
+    fn visit_return(&mut self, stmt: &stmt::Return) -> Result<(), LoxError> {
+        if self.current_function == FunctionType::None {
+            self.error(
+                ErrorCode::ReturnFromTopLevel,
+                &stmt.keyword,
+                "Can't return from top-level code.",
+            );
+        }
+        if let Some(value) = &stmt.value {
+            if self.current_function == FunctionType::Initializer {
+                self.error(
+                    ErrorCode::ReturnValueFromInitializer,
+                    &stmt.keyword,
+                    "Can't return a value from an initializer.",
+                );
+            }
+            self.resolve_expr(value);
+        }
+        Ok(())
+    }
+
+    fn visit_var(&mut self, stmt: &stmt::Var) -> Result<(), LoxError> {
+        self.declare(&stmt.name, BindingKind::Variable);
+        match &stmt.initializer {
+            Some(initializer) => {
+                self.resolve_expr(initializer);
+                if let Some(scope) = self.scopes.last_mut() {
+                    if let Some(binding) = scope.get_mut(stmt.name.lexeme.as_ref()) {
+                        binding.assigned = true;
+                    }
+                }
+            }
+            None if self.scopes.is_empty() => {
+                self.unassigned_globals.insert(stmt.name.lexeme.to_string());
+            }
+            None => {}
+        }
+        self.define(&stmt.name);
+        Ok(())
+    }
+
+    fn visit_while(&mut self, stmt: &stmt::While) -> Result<(), LoxError> {
+        self.resolve_loop(stmt);
+        Ok(())
+    }
+}
+
+#[test]
+fn test_resolver() {
+    use crate::parse::Parser;
+    use crate::scanner::Scanner;
+
+    fn resolve(source: &str) -> Result<Vec<Stmt>, Vec<LoxError>> {
+        let statements = Parser::new(Scanner::new(source)).parse().unwrap();
+        Resolver::default().resolve(&statements)?;
+        Ok(statements)
+    }
+
+    let statements = resolve("var a = 1; { var b = a; { print b; } }").unwrap();
+    let Stmt::Block(outer) = &statements[1] else {
+        unreachable!()
+    };
+    let Stmt::Block(inner) = &outer.statements[1] else {
+        unreachable!()
+    };
+    let Stmt::Print(print) = &inner.statements[0] else {
+        unreachable!()
+    };
+    let Expr::Variable(b) = &print.expression else {
+        unreachable!()
+    };
+    assert_eq!(b.depth.get(), Some(1));
+
+    assert_eq!(resolve("{ var a = a; }").unwrap_err().len(), 1);
+    assert_eq!(resolve("{ var a; var a; }").unwrap_err().len(), 1);
+    assert_eq!(
+        resolve("return 1; print this; print super.x;")
+            .unwrap_err()
+            .len(),
+        3
+    );
+    assert_eq!(
+        resolve("class A { f() { super.f(); } }").unwrap_err().len(),
+        1
+    );
+}
+
+#[test]
+fn test_resolver_warnings() {
+    use crate::parse::Parser;
+    use crate::scanner::Scanner;
+
+    fn warnings(source: &str) -> Vec<(ErrorCode, usize)> {
+        let statements = Parser::new(Scanner::new(source)).parse().unwrap();
+        let mut resolver = Resolver::default();
+        resolver.resolve(&statements).unwrap();
+        resolver
+            .warnings()
+            .iter()
+            .map(|warning| (warning.code, warning.line))
+            .collect()
+    }
+
+    assert_eq!(
+        warnings("fun f(a, _b) {\n  var c = 1;\n  c = 2;\n}"),
+        vec![
+            (ErrorCode::UnusedVariable, 1),
+            (ErrorCode::UnusedVariable, 2)
+        ]
+    );
+    assert_eq!(
+        warnings("fun f(a) {\n  if (a) return 1; else { return 2; }\n  print a;\n}"),
+        vec![(ErrorCode::UnreachableCode, 3)]
+    );
+    // The warning is at the first dead statement with a position, or at
+    // the `return` when none has one.
+    assert_eq!(
+        warnings("fun f() {\n  return;\n  \"ignored\";\n  {}\n  (1 + g)();\n}"),
+        vec![(ErrorCode::UnreachableCode, 5)]
+    );
+    assert_eq!(
+        warnings("fun f() {\n  return;\n  1;\n}"),
+        vec![(ErrorCode::UnreachableCode, 2)]
+    );
+    assert!(warnings("fun f(a) {\n  while (a) return 1;\n  print a;\n}").is_empty());
+    assert_eq!(
+        warnings("{ var a = 1;\n  { var a = 2; print a; } print a; }"),
+        vec![(ErrorCode::ShadowedVariable, 2)]
+    );
+    assert_eq!(
+        warnings("var g;\nprint g;\n{ var a;\n  fun f() { print a; }\n  print a;\n  a = 1; f(); }"),
+        vec![
+            (ErrorCode::UninitializedRead, 2),
+            (ErrorCode::UninitializedRead, 5)
+        ]
+    );
+    assert!(warnings("var g; g = 1; print g; { var a; a = 1; print a; }").is_empty());
+    // The next iteration sees what the loop body assigns.
+    let remember = "var prev;\nfor (var i = 0; i < 3; i = i + 1) {\n  if (prev != nil) print prev;\n  prev = i;\n}";
+    assert!(warnings(remember).is_empty());
+    assert!(warnings(&format!("fun f() {{\n{}\n}}\nf();", remember)).is_empty());
+    assert!(
+        warnings("{ var a;\n  while (true) {\n    while (true) print a;\n    a = 1;\n  }\n}")
+            .is_empty()
+    );
+    assert_eq!(
+        warnings("{ var a;\n  while (true) print a;\n}\nwhile (true) {\n  var b;\n  print b;\n  b = 1;\n}"),
+        vec![
+            (ErrorCode::UninitializedRead, 2),
+            (ErrorCode::UninitializedRead, 6)
+        ]
+    );
+    assert_eq!(
+        warnings("var a; a = 1;\nprint a == nil;\nprint \"${a}\" == nil;\nprint nil != (1 + a);"),
+        vec![(ErrorCode::NilComparison, 3), (ErrorCode::NilComparison, 4)]
+    );
+}
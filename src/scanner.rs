@@ -1,42 +1,106 @@
+use std::borrow::Cow;
+
 use crate::{
-    error::LoxError,
+    error::{ErrorCode, LoxError},
     literal::Object,
-    token::{Token, TokenType},
+    token::{Span, Token, TokenTrivia, TokenType, Trivia, TriviaKind},
+    unicode,
 };
 
-pub struct Scanner {
-    source: Vec<char>,
-    tokens: Vec<Token>,
+/// A `${` whose closing `}` hasn't been scanned yet.
+struct OpenInterpolation {
+    /// Unmatched `{` seen inside the interpolated expression.
+    depth: usize,
+    line: usize,
+    column: usize,
+    span: Span,
+}
+
+/// The reserved words, which scan as their own token types rather than
+/// as identifiers.
+pub const KEYWORDS: &[(&str, TokenType)] = &[
+    ("and", TokenType::And),
+    ("class", TokenType::Class),
+    ("else", TokenType::Else),
+    ("false", TokenType::False),
+    ("true", TokenType::True),
+    ("for", TokenType::For),
+    ("if", TokenType::If),
+    ("fun", TokenType::Fun),
+    ("nil", TokenType::Nil),
+    ("or", TokenType::Or),
+    ("print", TokenType::Print),
+    ("return", TokenType::Return),
+    ("super", TokenType::Super),
+    ("this", TokenType::This),
+    ("var", TokenType::Var),
+    ("while", TokenType::While),
+];
+
+/// Walks the UTF-8 source in place. `start` and `current` are byte offsets,
+/// and every token borrows its lexeme straight from the source.
+pub struct Scanner<'src> {
+    source: &'src str,
+    /// Set once the `Eof` token has been produced.
+    done: bool,
     start: usize,
     current: usize,
     line: usize,
+    /// 1-based column of `current`.
+    column: usize,
+    start_line: usize,
+    start_column: usize,
+    /// Interpolations being scanned, innermost last.
+    interpolations: Vec<OpenInterpolation>,
+    /// Whether tokens keep the whitespace and comments around them.
+    lossless: bool,
+    /// The `Error` token to yield after the error it stands for.
+    pending: Option<Token<'src>>,
 }
 
-impl Scanner {
+impl<'src> Scanner<'src> {
     fn is_at_end(&self) -> bool {
         self.current >= self.source.len()
     }
 
-    fn scan_token(&mut self) -> Result<(), LoxError> {
+    /// Scans one lexeme starting at `start`. Whitespace and comments
+    /// produce no token.
+    fn scan_token(&mut self) -> Result<Option<Token<'src>>, LoxError> {
         let c = self.advance();
-        match c {
-            '(' => self.add_token(TokenType::LeftParen),
-            ')' => self.add_token(TokenType::RightParen),
-            '{' => self.add_token(TokenType::LeftBrace),
-            '}' => self.add_token(TokenType::RightBrace),
-            ',' => self.add_token(TokenType::Comma),
-            '.' => self.add_token(TokenType::Dot),
-            '-' => self.add_token(TokenType::Minus),
-            '+' => self.add_token(TokenType::Plus),
-            ';' => self.add_token(TokenType::Semicolon),
-            '*' => self.add_token(TokenType::Star),
+        let token = match c {
+            '(' => Some(self.make_token(TokenType::LeftParen)),
+            ')' => Some(self.make_token(TokenType::RightParen)),
+            '{' => {
+                if let Some(interpolation) = self.interpolations.last_mut() {
+                    interpolation.depth += 1;
+                }
+                Some(self.make_token(TokenType::LeftBrace))
+            }
+            '}' => match self.interpolations.last_mut() {
+                Some(interpolation) if interpolation.depth == 0 => {
+                    // The end of an interpolated expression: back to the string.
+                    self.interpolations.pop();
+                    Some(self.string_segment(true)?)
+                }
+                Some(interpolation) => {
+                    interpolation.depth -= 1;
+                    Some(self.make_token(TokenType::RightBrace))
+                }
+                None => Some(self.make_token(TokenType::RightBrace)),
+            },
+            ',' => Some(self.make_token(TokenType::Comma)),
+            '.' => Some(self.make_token(TokenType::Dot)),
+            '-' => Some(self.make_token(TokenType::Minus)),
+            '+' => Some(self.make_token(TokenType::Plus)),
+            ';' => Some(self.make_token(TokenType::Semicolon)),
+            '*' => Some(self.make_token(TokenType::Star)),
             '!' => {
                 let token_type = if self.is_match('=') {
                     TokenType::BangEqual
                 } else {
                     TokenType::Bang
                 };
-                self.add_token(token_type)
+                Some(self.make_token(token_type))
             }
             '=' => {
                 let token_type = if self.is_match('=') {
@@ -44,7 +108,7 @@ impl Scanner {
                 } else {
                     TokenType::Equal
                 };
-                self.add_token(token_type)
+                Some(self.make_token(token_type))
             }
             '<' => {
                 let token_type = if self.is_match('=') {
@@ -52,7 +116,7 @@ impl Scanner {
                 } else {
                     TokenType::Less
                 };
-                self.add_token(token_type)
+                Some(self.make_token(token_type))
             }
             '>' => {
                 let token_type = if self.is_match('=') {
@@ -60,79 +124,173 @@ impl Scanner {
                 } else {
                     TokenType::Greater
                 };
-                self.add_token(token_type)
+                Some(self.make_token(token_type))
             }
             '/' => {
                 if self.is_match('/') {
-                    // A comment goes until the end of the line.
-                    while let Some(ch) = self.peek() {
-                        if ch != '\n' {
-                            self.advance();
-                        }
-                    }
+                    // `///` starts a doc comment, but `////` doesn't.
+                    let doc = self.peek() == Some('/') && self.peek_next() != Some('/');
+                    self.line_comment();
+                    doc.then(|| self.doc_comment())
                 } else if self.is_match('*') {
-                    // comments
+                    // `/**` starts a doc comment, but `/***` and `/**/` don't.
+                    let doc =
+                        self.peek() == Some('*') && !matches!(self.peek_next(), Some('*' | '/'));
                     self.scan_comment()?;
+                    doc.then(|| self.doc_comment())
                 } else {
-                    self.add_token(TokenType::Slash)
+                    Some(self.make_token(TokenType::Slash))
                 }
             }
-            ' ' | '\r' | '\t' => {}
-            '\n' => self.line += 1,
-            '"' => self.string()?,
+            ' ' | '\r' | '\t' | '\n' => None,
+            '"' => Some(self.string(false)?),
 
             // Reserved Words and Identifiers.
-            'o' => {
-                if self.is_match('r') {
-                    self.add_token(TokenType::Or);
-                }
-            }
             ch => {
                 if Scanner::is_digit(ch) {
-                    self.number()?;
+                    Some(self.number(ch)?)
+                } else if ch == 'r' && self.is_match('"') {
+                    Some(self.string(true)?)
                 } else if Scanner::is_alpha(ch) {
-                    self.identifier();
+                    Some(self.identifier())
                 } else {
-                    return Err(LoxError::error(
-                        self.line,
+                    return Err(self.error(
+                        ErrorCode::UnexpectedCharacter,
                         format!("Unexpected character: {}", ch),
                     ));
                 }
             }
+        };
+        Ok(token)
+    }
+
+    /// Skips the rest of a `//` comment, which goes until the end of the line.
+    fn line_comment(&mut self) {
+        while let Some(ch) = self.peek() {
+            if ch == '\n' {
+                break;
+            }
+            self.advance();
+        }
+    }
+
+    fn at_line_doc_comment(&self) -> bool {
+        let rest = &self.source[self.current..];
+        rest.starts_with("///") && !rest.starts_with("////")
+    }
+
+    /// Builds a doc comment token whose literal is the text of the comment
+    /// without its delimiters, and for block comments without the
+    /// indentation and leading `*` of each line.
+    fn doc_comment(&self) -> Token<'src> {
+        let lexeme = &self.source[self.start..self.current];
+        let text = if let Some(line) = lexeme.strip_prefix("///") {
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            line.strip_prefix(' ').unwrap_or(line).to_string()
+        } else {
+            let body = &lexeme[3..lexeme.len() - 2];
+            let lines = body
+                .lines()
+                .map(|line| {
+                    let line = line.trim_start();
+                    let line = line.strip_prefix('*').unwrap_or(line);
+                    line.strip_prefix(' ').unwrap_or(line).trim_end()
+                })
+                .collect::<Vec<_>>();
+            let first = lines.iter().position(|line| !line.is_empty());
+            let last = lines.iter().rposition(|line| !line.is_empty());
+            match (first, last) {
+                (Some(first), Some(last)) => lines[first..=last].join("\n"),
+                _ => String::new(),
+            }
+        };
+        self.make_literal_token(TokenType::DocComment, Object::String(text))
+    }
+
+    /// Records the whitespace or comment just skipped as trivia, merging
+    /// adjacent whitespace into one piece.
+    fn push_trivia(&self, trivia: &mut Vec<Trivia<'src>>) {
+        let text = &self.source[self.start..self.current];
+        let kind = if text == "\n" {
+            TriviaKind::Newline
+        } else if text.starts_with("//") {
+            TriviaKind::LineComment
+        } else if text.starts_with("/*") {
+            TriviaKind::BlockComment
+        } else {
+            TriviaKind::Whitespace
+        };
+        if let Some(last) = trivia.last_mut() {
+            if kind == TriviaKind::Whitespace
+                && last.kind == TriviaKind::Whitespace
+                && last.span.end == self.start
+            {
+                last.span.end = self.current;
+                last.text = Cow::Borrowed(&self.source[last.span.start..self.current]);
+                return;
+            }
+        }
+        trivia.push(Trivia {
+            kind,
+            text: Cow::Borrowed(text),
+            span: Span::new(self.start, self.current),
+        });
+    }
+
+    /// Scans the whitespace and `//` comment that follow a token on its line.
+    fn trailing_trivia(&mut self) -> Vec<Trivia<'src>> {
+        let mut trivia = Vec::new();
+        loop {
+            self.start = self.current;
+            match self.peek() {
+                Some(' ' | '\t' | '\r') => {
+                    self.advance();
+                }
+                // A doc comment is a token of its own, not trivia.
+                Some('/')
+                    if self.source[self.current..].starts_with("//")
+                        && !self.at_line_doc_comment() =>
+                {
+                    self.line_comment()
+                }
+                _ => return trivia,
+            }
+            self.push_trivia(&mut trivia);
         }
-        Ok(())
     }
 
+    /// Block comments nest. The depth is counted rather than recursed on,
+    /// so that no amount of nesting can overflow the stack.
     fn scan_comment(&mut self) -> Result<(), LoxError> {
+        let mut depth = 1;
         loop {
             match self.peek() {
                 Some('*') => {
                     self.advance();
                     if self.is_match('/') {
                         if self.is_match('*') {
-                            self.scan_comment()?;
+                            depth += 1;
                         } else {
-                            return Ok(());
+                            depth -= 1;
+                            if depth == 0 {
+                                return Ok(());
+                            }
                         }
                     }
                 }
                 Some('/') => {
                     self.advance();
                     if self.is_match('*') {
-                        self.scan_comment()?;
+                        depth += 1;
                     }
                 }
-                Some('\n') => {
-                    self.advance();
-                    self.line += 1;
-                }
                 Some(_ch) => {
                     self.advance();
                 }
                 None => {
                     // at the end of the file.
-                    return Err(LoxError::error(
-                        self.line,
+                    return Err(self.error(
+                        ErrorCode::UnterminatedComment,
                         "Unterminated comments".to_string(),
                     ));
                 }
@@ -140,7 +298,7 @@ impl Scanner {
         }
     }
 
-    fn identifier(&mut self) {
+    fn identifier(&mut self) -> Token<'src> {
         while let Some(ch) = self.peek() {
             if Scanner::is_alpha_numberic(ch) {
                 self.advance();
@@ -148,161 +306,925 @@ impl Scanner {
                 break;
             }
         }
-        let text: String = String::from_iter(self.source[self.start..self.current].into_iter());
-        let tt = match text.as_str() {
-            "and" => TokenType::And,
-            "class" => TokenType::Class,
-            "else" => TokenType::Else,
-            "false" => TokenType::False,
-            "true" => TokenType::True,
-            "for" => TokenType::For,
-            "if" => TokenType::If,
-            "fun" => TokenType::Fun,
-            "nil" => TokenType::Nil,
-            "or" => TokenType::Or,
-            "print" => TokenType::Print,
-            "return" => TokenType::Return,
-            "super" => TokenType::Super,
-            "this" => TokenType::This,
-            "var" => TokenType::Var,
-            "while" => TokenType::While,
-            _ => {
-                self.add_token(TokenType::Identifier);
-                return;
-            }
-        };
-        self.add_token(tt);
+        let text = &self.source[self.start..self.current];
+        let tt = KEYWORDS
+            .iter()
+            .find(|(keyword, _)| *keyword == text)
+            .map_or(TokenType::Identifier, |(_, tt)| *tt);
+        let mut token = self.make_token(tt);
+        if self.lossless {
+            return token;
+        }
+        if let Cow::Owned(name) = unicode::nfc(&token.lexeme) {
+            token.lexeme = Cow::Owned(name);
+        }
+        token
     }
 
+    /// Identifiers follow UAX #31, with `_` allowed anywhere.
     fn is_alpha(c: char) -> bool {
-        c >= 'a' && c <= 'z' || c >= 'A' && c <= 'Z' || c == '_'
+        c.is_ascii_alphabetic() || c == '_' || (!c.is_ascii() && unicode::is_xid_start(c))
     }
 
     fn is_alpha_numberic(c: char) -> bool {
-        Scanner::is_digit(c) || Scanner::is_alpha(c)
+        c.is_ascii_alphanumeric() || c == '_' || (!c.is_ascii() && unicode::is_xid_continue(c))
     }
 
-    fn string(&mut self) -> Result<(), LoxError> {
-        while let Some(ch) = self.peek() {
-            if !self.is_at_end() && ch != '"' {
-                if ch == '\n' {
-                    self.line += 1;
+    /// Scans a string literal whose opening quote has been consumed.
+    /// `"""` opens a multi-line string whose common indentation is
+    /// stripped. Raw strings (`r"..."`) take their contents verbatim;
+    /// all others have their escape sequences decoded, and plain `"..."`
+    /// strings may interpolate `${expression}`.
+    fn string(&mut self, raw: bool) -> Result<Token<'src>, LoxError> {
+        let triple = self.source[self.current..].starts_with("\"\"");
+        if triple {
+            self.advance();
+            self.advance();
+        } else if !raw {
+            return self.string_segment(false);
+        }
+
+        let content_start = self.current;
+        loop {
+            match self.peek() {
+                None => {
+                    return Err(self.error(
+                        ErrorCode::UnterminatedString,
+                        "Unterminated string.".to_string(),
+                    ))
+                }
+                Some('"') if !triple || self.source[self.current..].starts_with("\"\"\"") => break,
+                Some('\\') if !raw => {
+                    // Skip the escaped character so `\"` doesn't end the string.
+                    self.advance();
+                    if !self.is_at_end() {
+                        self.advance();
+                    }
+                }
+                Some(_) => {
+                    self.advance();
                 }
-                self.advance();
-            } else {
-                break;
             }
         }
+        let content_end = self.current;
 
-        if self.is_at_end() {
-            return Err(LoxError::error(
-                self.line,
-                "Unterminated string.".to_string(),
-            ));
+        // The closing quotes.
+        self.advance();
+        if triple {
+            self.advance();
+            self.advance();
         }
 
-        // The closing '"'.
+        let value = if triple {
+            self.dedent(content_start, content_end, raw)?
+        } else if raw {
+            self.source[content_start..content_end].to_string()
+        } else {
+            self.unescape(content_start, content_end)?
+        };
+        Ok(self.make_literal_token(TokenType::String, Object::String(value)))
+    }
+
+    /// Scans a plain string up to its closing quote, or up to a `${`, in
+    /// which case an `Interpolation` token is produced and the scanner
+    /// returns to ordinary tokens until the matching `}`. Past that `}`,
+    /// which `continued` tells, the segments get token types of their own
+    /// so that the parser can't mistake one for the start of a string.
+    fn string_segment(&mut self, continued: bool) -> Result<Token<'src>, LoxError> {
+        let content_start = self.current;
+        loop {
+            match self.peek() {
+                None => {
+                    return Err(self.error(
+                        ErrorCode::UnterminatedString,
+                        "Unterminated string.".to_string(),
+                    ))
+                }
+                Some('"') => break,
+                Some('$') if self.peek_next() == Some('{') => {
+                    let content_end = self.current;
+                    self.interpolations.push(OpenInterpolation {
+                        depth: 0,
+                        line: self.line,
+                        column: self.column,
+                        span: Span::new(self.current, self.current + 2),
+                    });
+                    self.advance();
+                    self.advance();
+                    let value = self.unescape(content_start, content_end)?;
+                    let token_type = if continued {
+                        TokenType::InterpolationMiddle
+                    } else {
+                        TokenType::Interpolation
+                    };
+                    return Ok(self.make_literal_token(token_type, Object::String(value)));
+                }
+                Some('\\') => {
+                    self.advance();
+                    if !self.is_at_end() {
+                        self.advance();
+                    }
+                }
+                Some(_) => {
+                    self.advance();
+                }
+            }
+        }
+        let content_end = self.current;
         self.advance();
+        let value = self.unescape(content_start, content_end)?;
+        let token_type = if continued {
+            TokenType::InterpolationEnd
+        } else {
+            TokenType::String
+        };
+        Ok(self.make_literal_token(token_type, Object::String(value)))
+    }
+
+    /// Builds the value of a triple-quoted string from its source lines.
+    /// A line break right after the opening quotes is dropped, as is the
+    /// line holding only the closing quotes, whose indentation still
+    /// counts towards the common indent removed from every line.
+    fn dedent(&self, start: usize, end: usize, raw: bool) -> Result<String, LoxError> {
+        let mut lines = Vec::new();
+        let mut line_start = start;
+        for line in self.source[start..end].split('\n') {
+            // A CRLF line break is one break, like LF.
+            lines.push((line_start, line.strip_suffix('\r').unwrap_or(line)));
+            line_start += line.len() + 1;
+        }
 
-        let value = String::from_iter(self.source[self.start + 1..self.current - 1].into_iter());
-        self.add_token_string(TokenType::String, Some(Object::String(value)));
+        let is_blank = |line: &str| line.trim_start_matches([' ', '\t']).is_empty();
+        let indent_of = |line: &str| line.len() - line.trim_start_matches([' ', '\t']).len();
 
-        Ok(())
+        if lines.len() > 1 && is_blank(lines[0].1) {
+            lines.remove(0);
+        }
+        let mut indent = usize::MAX;
+        if lines.len() > 1 && is_blank(lines[lines.len() - 1].1) {
+            let (_, closing) = lines.pop().unwrap();
+            indent = closing.len();
+        }
+        for (_, line) in lines.iter().filter(|(_, line)| !is_blank(line)) {
+            indent = indent.min(indent_of(line));
+        }
+
+        let mut value = Vec::with_capacity(lines.len());
+        for (offset, line) in lines {
+            let strip = indent.min(indent_of(line));
+            let (from, to) = (offset + strip, offset + line.len());
+            if raw {
+                value.push(self.source[from..to].to_string());
+            } else {
+                value.push(self.unescape(from, to)?);
+            }
+        }
+        Ok(value.join("\n"))
     }
 
-    fn peek(&mut self) -> Option<char> {
-        if self.is_at_end() {
-            return None;
+    /// Decodes the escape sequences in `source[start..end]`.
+    fn unescape(&self, start: usize, end: usize) -> Result<String, LoxError> {
+        let text = &self.source[start..end];
+        let mut value = String::with_capacity(text.len());
+        let mut chars = text.char_indices().peekable();
+
+        while let Some((index, ch)) = chars.next() {
+            if ch != '\\' {
+                value.push(ch);
+                continue;
+            }
+
+            let escape_start = start + index;
+            let escaped = match chars.next() {
+                Some((_, 'n')) => '\n',
+                Some((_, 't')) => '\t',
+                Some((_, 'r')) => '\r',
+                Some((_, '0')) => '\0',
+                Some((_, '\\')) => '\\',
+                Some((_, '"')) => '"',
+                Some((_, '\'')) => '\'',
+                Some((_, '$')) => '$',
+                Some((_, 'u')) => {
+                    let mut digits = String::new();
+                    let mut closed = false;
+                    if chars.next_if(|(_, ch)| *ch == '{').is_some() {
+                        while let Some((_, ch)) = chars.next_if(|(_, ch)| *ch != '"') {
+                            if ch == '}' {
+                                closed = true;
+                                break;
+                            }
+                            digits.push(ch);
+                        }
+                    }
+                    let escape_end = chars.peek().map_or(end, |(index, _)| start + index);
+                    let span = Span::new(escape_start, escape_end);
+                    if !closed {
+                        return Err(self.error_in_literal(
+                            span,
+                            "Malformed unicode escape, expected '\\u{XXXX}'.".to_string(),
+                        ));
+                    }
+                    if digits.is_empty()
+                        || digits.len() > 6
+                        || !digits.chars().all(|ch| ch.is_ascii_hexdigit())
+                    {
+                        return Err(self.error_in_literal(
+                            span,
+                            format!(
+                                "Invalid unicode escape '\\u{{{}}}': expected 1 to 6 hex digits.",
+                                digits
+                            ),
+                        ));
+                    }
+                    let code = u32::from_str_radix(&digits, 16).unwrap();
+                    match char::from_u32(code) {
+                        Some(ch) => ch,
+                        None => {
+                            return Err(self.error_in_literal(
+                                span,
+                                format!(
+                                "Invalid unicode escape '\\u{{{}}}': not a unicode scalar value.",
+                                digits
+                            ),
+                            ))
+                        }
+                    }
+                }
+                Some((index, other)) => {
+                    let span = Span::new(escape_start, start + index + other.len_utf8());
+                    return Err(self.error_in_literal(
+                        span,
+                        format!("Unknown escape sequence '\\{}'.", other),
+                    ));
+                }
+                None => {
+                    let span = Span::new(escape_start, escape_start + 1);
+                    return Err(
+                        self.error_in_literal(span, "Incomplete escape sequence.".to_string())
+                    );
+                }
+            };
+            value.push(escaped);
         }
-        // char_at(self.source.as_str(), self.current)
-        Some(*self.source.get(self.current).unwrap())
+        Ok(value)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.source[self.current..].chars().next()
     }
 
     fn is_match(&mut self, expected: char) -> bool {
-        if self.is_at_end() {
-            return false;
+        if self.peek() == Some(expected) {
+            self.advance();
+            true
+        } else {
+            false
         }
-        match self.source.get(self.current) {
-            Some(v) if *v == expected => {
-                self.current += 1;
-                true
-            }
-            _ => false,
+    }
+
+    fn make_token(&self, token_type: TokenType) -> Token<'src> {
+        Token {
+            token_type,
+            lexeme: Cow::Borrowed(&self.source[self.start..self.current]),
+            literal: None,
+            line: self.start_line,
+            column: self.start_column,
+            span: Span::new(self.start, self.current),
+            trivia: None,
         }
     }
 
-    fn add_token(&mut self, token_type: TokenType) {
-        self.add_token_string(token_type, None)
+    fn make_literal_token(&self, token_type: TokenType, literal: Object) -> Token<'src> {
+        Token {
+            literal: Some(literal),
+            ..self.make_token(token_type)
+        }
     }
 
-    fn add_token_string(&mut self, token_type: TokenType, literal: Option<Object>) {
-        let lexeme = String::from_iter(self.source[self.start..self.current].iter());
-        self.tokens
-            .push(Token::new(token_type, lexeme, literal, self.line));
+    /// Builds an error located at the start of the current lexeme.
+    fn error(&self, code: ErrorCode, message: String) -> LoxError {
+        LoxError::lexical(
+            code,
+            self.start_line,
+            self.start_column,
+            Span::new(self.start, self.current),
+            message,
+        )
+    }
+
+    /// Builds an error pointing at one bad escape sequence inside the
+    /// current lexeme, which may span several lines.
+    fn error_in_literal(&self, span: Span, message: String) -> LoxError {
+        let before = &self.source[self.start..span.start];
+        let (line, column) = match before.rfind('\n') {
+            Some(newline) => (
+                self.start_line + before.matches('\n').count(),
+                before[newline + 1..].chars().count() + 1,
+            ),
+            None => (self.start_line, self.start_column + before.chars().count()),
+        };
+        LoxError::lexical(ErrorCode::InvalidEscape, line, column, span, message)
     }
 
+    /// Consumes one character. All position bookkeeping happens here so
+    /// that lines, columns and byte offsets stay right through strings and
+    /// multi-line comments.
     fn advance(&mut self) -> char {
-        let ch = *self.source.get(self.current).unwrap();
-        self.current += 1;
+        let ch = self.peek().unwrap();
+        self.current += ch.len_utf8();
+        if ch == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
         ch
     }
-    pub fn scan_tokens(&mut self) -> Result<&Vec<Token>, LoxError> {
-        while !self.is_at_end() {
-            self.start = self.current;
-            self.scan_token()?;
+    /// Scans the whole source. A lexical error does not stop the scanner:
+    /// the offending character or literal becomes an `Error` token and the
+    /// error is returned alongside the tokens, so every problem in the file
+    /// is reported at once.
+    pub fn scan_tokens(self) -> (Vec<Token<'src>>, Vec<LoxError>) {
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+        for result in self {
+            match result {
+                Ok(token) => tokens.push(token),
+                Err(err) => errors.push(err),
+            }
         }
-
-        self.tokens.push(Token::eof(self.line));
-        Ok(&self.tokens)
+        (tokens, errors)
     }
 
-    pub fn new(source: String) -> Scanner {
+    pub fn new(source: &'src str) -> Scanner<'src> {
         Scanner {
-            source: source.chars().collect(),
-            tokens: Vec::new(),
+            source,
+            done: false,
             start: 0,
             current: 0,
             line: 1,
+            column: 1,
+            start_line: 1,
+            start_column: 1,
+            interpolations: Vec::new(),
+            lossless: false,
+            pending: None,
+        }
+    }
+
+    /// A scanner whose tokens carry their surrounding whitespace and
+    /// comments as trivia, so that concatenating the leading trivia,
+    /// lexeme and trailing trivia of every token reproduces the source
+    /// byte for byte. Identifiers keep their spelling instead of being
+    /// normalized. This holds for sources with lexical errors too, whose
+    /// text is kept in `Error` tokens.
+    pub fn lossless(source: &'src str) -> Scanner<'src> {
+        Scanner {
+            lossless: true,
+            ..Scanner::new(source)
         }
     }
 
     fn is_digit(c: char) -> bool {
-        c >= '0' && c <= '9'
+        c.is_ascii_digit()
     }
 
-    fn number(&mut self) -> Result<(), LoxError> {
-        while let Some(ch) = self.peek() {
-            if Scanner::is_digit(ch) {
+    /// Scans a number literal whose first digit has been consumed: a
+    /// decimal with optional fraction and exponent, or a `0x`, `0o` or `0b`
+    /// integer. Digits may be grouped with single `_` separators.
+    fn number(&mut self, first: char) -> Result<Token<'src>, LoxError> {
+        let radix = match (first, self.peek()) {
+            ('0', Some('x')) => 16,
+            ('0', Some('o')) => 8,
+            ('0', Some('b')) => 2,
+            _ => 10,
+        };
+
+        let value = if radix == 10 {
+            self.digits(10, true)?;
+
+            // Look for a fractional part.
+            if self.peek() == Some('.') && self.peek_next().is_some_and(Scanner::is_digit) {
+                // Consume the '.'
                 self.advance();
-            } else {
-                break;
+                self.digits(10, false)?;
             }
+
+            // And an exponent.
+            if matches!(self.peek(), Some('e' | 'E')) {
+                self.advance();
+                if matches!(self.peek(), Some('+' | '-')) {
+                    self.advance();
+                }
+                if self.digits(10, false)? == 0 {
+                    return Err(self.malformed_number("Expected digits in exponent.".to_string()));
+                }
+            }
+
+            let mut num_string = Cow::Borrowed(&self.source[self.start..self.current]);
+            if num_string.contains('_') {
+                num_string = Cow::Owned(num_string.replace('_', ""));
+            }
+            match num_string.parse() {
+                Ok(value) => value,
+                Err(_) => {
+                    return Err(
+                        self.malformed_number(format!("Invalid number literal '{}'.", num_string))
+                    )
+                }
+            }
+        } else {
+            // Consume the radix prefix.
+            let prefix = self.advance();
+            let digits_start = self.current;
+            if self.digits(radix, false)? == 0 {
+                return Err(self.malformed_number(format!(
+                    "Expected {} digits after '0{}'.",
+                    Scanner::radix_name(radix),
+                    prefix
+                )));
+            }
+            self.source[digits_start..self.current]
+                .chars()
+                .filter_map(|ch| ch.to_digit(radix))
+                .fold(0.0, |value, digit| value * radix as f64 + digit as f64)
+        };
+
+        // A number must not run straight into a name or other digits.
+        if let Some(ch) = self.peek().filter(|ch| Scanner::is_alpha_numberic(*ch)) {
+            let message = if radix != 10 && ch.is_ascii_digit() {
+                format!(
+                    "Invalid digit '{}' in {} literal.",
+                    ch,
+                    Scanner::radix_name(radix)
+                )
+            } else {
+                format!("Invalid character '{}' in number literal.", ch)
+            };
+            return Err(self.malformed_number(message));
         }
+        if value.is_infinite() {
+            return Err(self.malformed_number(format!(
+                "Number literal '{}' is too large.",
+                &self.source[self.start..self.current]
+            )));
+        }
+
+        Ok(self.make_literal_token(TokenType::Number, Object::Num(value)))
+    }
 
-        // Look for a fractional part.
-        if self.is_match('.') {
-            while let Some(next) = self.peek_next() {
-                if Scanner::is_digit(next) {
-                    // Consume the '.'
+    /// Consumes a run of digits in `radix`, allowing a single `_` between
+    /// two digits. `after_digit` tells whether the run continues a digit
+    /// that was already consumed. Returns the number of digits consumed.
+    fn digits(&mut self, radix: u32, after_digit: bool) -> Result<usize, LoxError> {
+        let mut after_digit = after_digit;
+        let mut count = 0;
+        loop {
+            match self.peek() {
+                Some(ch) if ch.is_digit(radix) => {
                     self.advance();
-                } else {
-                    break;
+                    after_digit = true;
+                    count += 1;
+                }
+                Some('_') => {
+                    if !after_digit || !self.peek_next().is_some_and(|ch| ch.is_digit(radix)) {
+                        return Err(self.malformed_number(
+                            "Digit separator '_' must be between two digits.".to_string(),
+                        ));
+                    }
+                    self.advance();
+                    after_digit = false;
                 }
+                _ => return Ok(count),
             }
         }
+    }
 
-        let num_string = String::from_iter(self.source[self.start..self.current].iter());
-        self.add_token_string(
-            TokenType::Number,
-            Some(Object::Num(num_string.parse().unwrap())),
-        );
-        Ok(())
+    fn radix_name(radix: u32) -> &'static str {
+        match radix {
+            2 => "binary",
+            8 => "octal",
+            16 => "hexadecimal",
+            _ => "decimal",
+        }
+    }
+
+    /// Skips the rest of a malformed number so scanning resumes after it
+    /// rather than in the middle of it.
+    fn malformed_number(&mut self, message: String) -> LoxError {
+        while self.peek().is_some_and(Scanner::is_alpha_numberic) {
+            self.advance();
+        }
+        self.error(ErrorCode::MalformedNumber, message)
+    }
+
+    fn peek_next(&self) -> Option<char> {
+        let mut chars = self.source[self.current..].chars();
+        chars.next();
+        chars.next()
     }
+}
+
+/// Tokens are produced on demand, ending with a single `Eof` token. A
+/// lexical error is yielded, then an `Error` token holding the bad lexeme,
+/// and scanning resumes right after it.
+impl<'src> Iterator for Scanner<'src> {
+    type Item = Result<Token<'src>, LoxError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(token) = self.pending.take() {
+            return Some(Ok(token));
+        }
+        let mut leading_trivia = Vec::new();
+        while !self.is_at_end() {
+            self.start = self.current;
+            self.start_line = self.line;
+            self.start_column = self.column;
+            match self.scan_token() {
+                Ok(Some(mut token)) => {
+                    if self.lossless {
+                        token.trivia = Some(Box::new(TokenTrivia {
+                            leading: leading_trivia,
+                            trailing: self.trailing_trivia(),
+                        }));
+                    }
+                    return Some(Ok(token));
+                }
+                Ok(None) => {
+                    if self.lossless {
+                        self.push_trivia(&mut leading_trivia);
+                    }
+                }
+                Err(err) => {
+                    let mut token = self.make_token(TokenType::Error);
+                    if self.lossless {
+                        token.trivia = Some(Box::new(TokenTrivia {
+                            leading: leading_trivia,
+                            trailing: self.trailing_trivia(),
+                        }));
+                    }
+                    self.pending = Some(token);
+                    return Some(Err(err));
+                }
+            }
+        }
 
-    fn peek_next(&mut self) -> Option<char> {
-        if self.current + 1 >= self.source.len() {
+        if self.done {
             return None;
         }
-        Some(*self.source.get(self.current + 1).unwrap())
+        if let Some(interpolation) = self.interpolations.pop() {
+            // There is no lexeme to hold, but the parser still learns that
+            // the expression was cut short.
+            self.start = self.current;
+            self.start_line = self.line;
+            self.start_column = self.column;
+            let mut token = self.make_token(TokenType::Error);
+            if self.lossless {
+                token.trivia = Some(Box::new(TokenTrivia {
+                    leading: leading_trivia,
+                    trailing: Vec::new(),
+                }));
+            }
+            self.pending = Some(token);
+            return Some(Err(LoxError::lexical(
+                ErrorCode::UnterminatedInterpolation,
+                interpolation.line,
+                interpolation.column,
+                interpolation.span,
+                "Unterminated '${' in string interpolation.".to_string(),
+            )));
+        }
+        self.done = true;
+        let mut eof = Token::eof(self.line);
+        eof.column = self.column;
+        eof.span = Span::new(self.current, self.current);
+        if self.lossless {
+            eof.trivia = Some(Box::new(TokenTrivia {
+                leading: leading_trivia,
+                trailing: Vec::new(),
+            }));
+        }
+        Some(Ok(eof))
     }
 }
+
+#[test]
+fn test_scanner_reports_every_error() {
+    let (tokens, errors) = Scanner::new("var a = 1 @ 2;\nvar b = #;\nprint \"open").scan_tokens();
+    assert_eq!(errors.len(), 3);
+    let types = tokens.iter().map(|t| t.token_type).collect::<Vec<_>>();
+    assert_eq!(
+        types,
+        [
+            TokenType::Var,
+            TokenType::Identifier,
+            TokenType::Equal,
+            TokenType::Number,
+            TokenType::Error,
+            TokenType::Number,
+            TokenType::Semicolon,
+            TokenType::Var,
+            TokenType::Identifier,
+            TokenType::Equal,
+            TokenType::Error,
+            TokenType::Semicolon,
+            TokenType::Print,
+            TokenType::Error,
+            TokenType::Eof,
+        ]
+    );
+}
+
+#[test]
+fn test_scanner_positions() {
+    let source = "var a = \"é\nb\";\n/* x\n */ print a;";
+    let (tokens, errors) = Scanner::new(source).scan_tokens();
+    assert!(errors.is_empty());
+    for token in tokens.iter() {
+        assert_eq!(&source[token.span.start..token.span.end], token.lexeme);
+        assert!(matches!(token.lexeme, Cow::Borrowed(_)));
+    }
+
+    let string = &tokens[3];
+    assert_eq!((string.line, string.column), (1, 9));
+    let semicolon = &tokens[4];
+    assert_eq!((semicolon.line, semicolon.column), (2, 3));
+    let print = &tokens[5];
+    assert_eq!((print.line, print.column), (4, 5));
+    let ident = &tokens[6];
+    assert_eq!((ident.line, ident.column), (4, 11));
+    assert_eq!(ident.span, Span::new(31, 32));
+}
+
+#[test]
+fn test_scanner_is_lazy() {
+    let mut scanner = Scanner::new("print 1; @ var");
+    assert_eq!(
+        scanner.next().unwrap().unwrap().token_type,
+        TokenType::Print
+    );
+    assert_eq!(
+        scanner.next().unwrap().unwrap().token_type,
+        TokenType::Number
+    );
+    assert_eq!(
+        scanner.next().unwrap().unwrap().token_type,
+        TokenType::Semicolon
+    );
+    assert!(scanner.next().unwrap().is_err());
+    assert_eq!(
+        scanner.next().unwrap().unwrap().token_type,
+        TokenType::Error
+    );
+    assert_eq!(scanner.next().unwrap().unwrap().token_type, TokenType::Var);
+    assert_eq!(scanner.next().unwrap().unwrap().token_type, TokenType::Eof);
+    assert!(scanner.next().is_none());
+}
+
+#[test]
+fn test_scanner_nested_comments() {
+    let deep = 200_000;
+    let source = format!("{}{}print 1;", "/* ".repeat(deep), "*/ ".repeat(deep));
+    let (tokens, errors) = Scanner::new(&source).scan_tokens();
+    assert!(errors.is_empty());
+    assert_eq!(tokens.len(), 4);
+
+    let source = format!("{}{}", "/* ".repeat(deep), "*/ ".repeat(deep - 1));
+    let (_, errors) = Scanner::new(&source).scan_tokens();
+    assert_eq!(errors[0].code(), ErrorCode::UnterminatedComment);
+}
+
+#[test]
+fn test_scanner_string_literals() {
+    fn string(source: &str) -> Result<Object, LoxError> {
+        let mut scanner = Scanner::new(source);
+        scanner.next().unwrap().map(|token| token.literal.unwrap())
+    }
+    let text = |value: &str| Some(Object::String(value.to_string()));
+
+    assert_eq!(string(r#""a\tb\n\"c\"\\""#).ok(), text("a\tb\n\"c\"\\"));
+    assert_eq!(string(r#""\u{1F600}\u{e9}""#).ok(), text("\u{1F600}\u{e9}"));
+    assert_eq!(string(r#"r"C:\path\n""#).ok(), text("C:\\path\\n"));
+    assert_eq!(
+        string("\"\"\"\n    first\n      second\\t\n    \"\"\"").ok(),
+        text("first\n  second\t")
+    );
+    assert_eq!(string("r\"\"\"\n  raw \\n\n  \"\"\"").ok(), text("raw \\n"));
+    assert_eq!(
+        string("\"\"\"\r\n    first\r\n      second\r\n    \"\"\"").ok(),
+        text("first\n  second")
+    );
+    assert_eq!(
+        string("r\"\"\"\r\n  raw\r\n\r\n  \"\"\"").ok(),
+        text("raw\n")
+    );
+
+    for bad in [
+        r#""\q""#,
+        r#""\u{}""#,
+        r#""\u{110000}""#,
+        r#""\u{12""#,
+        r#""\u0041""#,
+    ] {
+        assert!(string(bad).is_err(), "{}", bad);
+    }
+
+    let err = string("\"\"\"\n  ok\n  bad \\x\n  \"\"\"").unwrap_err();
+    assert!(format!("{:?}", err).contains("line: 3, column: Some(7)"));
+}
+
+#[test]
+fn test_scanner_interpolation() {
+    let source = r#""a ${ {"x" "${b}"} } c \${d}""#;
+    let (tokens, errors) = Scanner::new(source).scan_tokens();
+    assert!(errors.is_empty());
+    let types = tokens.iter().map(|t| t.token_type).collect::<Vec<_>>();
+    assert_eq!(
+        types,
+        [
+            TokenType::Interpolation,
+            TokenType::LeftBrace,
+            TokenType::String,
+            TokenType::Interpolation,
+            TokenType::Identifier,
+            TokenType::InterpolationEnd,
+            TokenType::RightBrace,
+            TokenType::InterpolationEnd,
+            TokenType::Eof,
+        ]
+    );
+    assert_eq!(
+        tokens[7].literal,
+        Some(Object::String(" c ${d}".to_string()))
+    );
+
+    let (_, errors) = Scanner::new("print \"a ${b;").scan_tokens();
+    assert_eq!(errors.len(), 1);
+    assert!(format!("{:?}", errors[0]).contains("Unterminated '${'"));
+    assert!(format!("{:?}", errors[0]).contains("line: 1, column: Some(10)"));
+}
+
+#[test]
+fn test_scanner_numbers() {
+    fn number(source: &str) -> Result<Object, LoxError> {
+        let mut scanner = Scanner::new(source);
+        scanner.next().unwrap().map(|token| token.literal.unwrap())
+    }
+
+    for (source, value) in [
+        ("0xFF", 255.),
+        ("0xdead_beef", 3735928559.),
+        ("0b1010", 10.),
+        ("0o17", 15.),
+        ("1_000_000", 1e6),
+        ("1.5e-3", 1.5e-3),
+        ("2E+2", 200.),
+        ("1_0.2_5e1_0", 10.25e10),
+        ("0", 0.),
+    ] {
+        assert_eq!(number(source).ok(), Some(Object::Num(value)), "{}", source);
+    }
+
+    for source in [
+        "0x", "0b", "1__0", "1_", "0x_1", "1e", "1e+", "0b102", "0o8", "12ab", "0xFG",
+    ] {
+        assert!(number(source).is_err(), "{}", source);
+    }
+
+    // Literals too large for a double are errors rather than infinity,
+    // while tiny ones round to zero as usual.
+    let huge_hex = format!("0x{}", "F".repeat(300));
+    for source in ["1e400", "2_000e306", huge_hex.as_str()] {
+        let err = number(source).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::MalformedNumber);
+        assert!(err.to_string().ends_with("is too large."), "{}", source);
+    }
+    assert_eq!(number("1e-400").ok(), Some(Object::Num(0.)));
+    assert_eq!(
+        number("1.7976931348623157e308").ok(),
+        Some(Object::Num(f64::MAX))
+    );
+
+    // A malformed number becomes a single error token.
+    let (tokens, errors) = Scanner::new("0b102 + 1__0;").scan_tokens();
+    assert_eq!(errors.len(), 2);
+    let tokens = tokens
+        .iter()
+        .map(|t| (t.token_type, t.lexeme.as_ref()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        tokens,
+        [
+            (TokenType::Error, "0b102"),
+            (TokenType::Plus, "+"),
+            (TokenType::Error, "1__0"),
+            (TokenType::Semicolon, ";"),
+            (TokenType::Eof, "")
+        ]
+    );
+}
+
+#[test]
+fn test_scanner_unicode_identifiers() {
+    let (tokens, errors) =
+        Scanner::new("var größe = 1; print gro\u{308}ße + 名前 + ñ_2;").scan_tokens();
+    assert!(errors.is_empty());
+    assert_eq!(tokens[1].lexeme, "größe");
+    // The decomposed spelling is normalized to the same name, while the
+    // position still covers the source as written.
+    assert_eq!(tokens[6].lexeme, "größe");
+    assert_eq!(tokens[6].span, Span::new(23, 31));
+    assert_eq!(tokens[8].lexeme, "名前");
+    assert_eq!(tokens[10].lexeme, "ñ_2");
+
+    let (_, errors) = Scanner::new("var € = 1;").scan_tokens();
+    assert_eq!(errors.len(), 1);
+}
+
+#[test]
+fn test_scanner_lossless() {
+    fn text(tokens: &[Token]) -> String {
+        let mut text = String::new();
+        for token in tokens.iter() {
+            for trivia in token.leading_trivia().iter() {
+                text.push_str(&trivia.text);
+            }
+            text.push_str(&token.lexeme);
+            for trivia in token.trailing_trivia().iter() {
+                text.push_str(&trivia.text);
+            }
+        }
+        text
+    }
+
+    let source = "// header\r\nvar a = 1;  // one\n\n/* outer /* inner */ */\tprint \"a ${a}\" +gro\u{308}ße;\n  ";
+    let tokens = Scanner::lossless(source)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(text(&tokens), source);
+
+    // The text of lexical errors is kept too.
+    for source in [
+        "0x_1 + 2;",
+        "var x = 1 @ 2; // at",
+        "print \"bad \\q ${x}\";\n",
+        "print \"a ${b  ",
+        "print 1;\n  /* open /* nested */",
+    ] {
+        let tokens = Scanner::lossless(source).flatten().collect::<Vec<_>>();
+        assert_eq!(text(&tokens), source);
+    }
+
+    let kinds = |trivia: &[Trivia]| trivia.iter().map(|t| t.kind).collect::<Vec<_>>();
+    assert_eq!(
+        kinds(tokens[0].leading_trivia()),
+        [TriviaKind::LineComment, TriviaKind::Newline]
+    );
+    assert_eq!(
+        kinds(tokens[4].trailing_trivia()),
+        [TriviaKind::Whitespace, TriviaKind::LineComment]
+    );
+    assert_eq!(
+        kinds(tokens[5].leading_trivia()),
+        [
+            TriviaKind::Newline,
+            TriviaKind::Newline,
+            TriviaKind::BlockComment,
+            TriviaKind::Whitespace
+        ]
+    );
+    assert_eq!(tokens[5].leading_trivia()[2].span, Span::new(31, 54));
+
+    // The default scanner keeps no trivia.
+    let (tokens, _) = Scanner::new(source).scan_tokens();
+    assert!(tokens.iter().all(|t| t.trivia.is_none()));
+}
+
+#[test]
+fn test_scanner_doc_comments() {
+    let source = "/// Adds.\r\n///\n//// not docs\n/**\n * Block\n *   indented\n */\n/***/ /**/ /** short */";
+    let (tokens, errors) = Scanner::new(source).scan_tokens();
+    assert!(errors.is_empty());
+    let docs = tokens
+        .iter()
+        .filter(|t| t.token_type == TokenType::DocComment)
+        .map(|t| t.literal.clone().unwrap())
+        .collect::<Vec<_>>();
+    let text = |value: &str| Object::String(value.to_string());
+    assert_eq!(
+        docs,
+        [
+            text("Adds."),
+            text(""),
+            text("Block\n  indented"),
+            text("short")
+        ]
+    );
+
+    // Doc comments stay tokens in lossless mode rather than trivia.
+    let tokens = Scanner::lossless("var a; /// after\n")
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(tokens[3].token_type, TokenType::DocComment);
+    assert_eq!(tokens[2].trailing_trivia().len(), 1);
+}
@@ -0,0 +1,20 @@
+use std::rc::Rc;
+
+use crate::error::LoxError;
+use crate::expr::{Expr, Variable};
+use crate::token::Token;
+
+rlox_macros::define_ast! {
+    "Stmt",
+    [
+        "Block      : Vec<Stmt> statements",
+        "Class      : Token<'static> name, Option<Variable> superclass, Vec<Function> methods, Option<String> doc",
+        "Expression : Expr expression",
+        "Function   : Token<'static> name, Vec<Token<'static>> params, Rc<Vec<Stmt>> body, Option<String> doc",
+        "If         : Token<'static> keyword, Expr condition, Stmt then_branch, Option<Box<Stmt>> else_branch",
+        "Print      : Token<'static> keyword, Expr expression",
+        "Return     : Token<'static> keyword, Option<Expr> value",
+        "Var        : Token<'static> name, Option<Expr> initializer, Option<String> doc",
+        "While      : Token<'static> keyword, Expr condition, Stmt body"
+    ]
+}
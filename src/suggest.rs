@@ -0,0 +1,109 @@
+//! "Did you mean" suggestions for misspelled names.
+
+use crate::scanner::KEYWORDS;
+
+/// The candidate closest to `name` by edit distance, if any is close
+/// enough to be a likely typo: within a third of the name's length, and
+/// at most one edit for short names. Ties go to the alphabetically first
+/// candidate so the suggestion doesn't depend on hash map order.
+pub fn closest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let max = name.chars().count().max(3) / 3;
+    candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+/// The keyword closest to `name`, for identifiers like `retrun` that were
+/// meant to be one.
+pub fn keyword(name: &str) -> Option<&'static str> {
+    closest(name, KEYWORDS.iter().map(|(keyword, _)| *keyword))
+}
+
+/// The keywords that are expressions on their own, the only ones that
+/// make sense where a variable is expected.
+const VALUE_KEYWORDS: &[&str] = &["false", "nil", "super", "this", "true"];
+
+/// Like `keyword`, for a name used as a value, as in `print nill;`.
+pub fn value_keyword(name: &str) -> Option<&'static str> {
+    closest(name, VALUE_KEYWORDS.iter().copied())
+}
+
+/// The keywords that begin a statement, so a name standing alone as one
+/// is likely one of them mistyped.
+const STATEMENT_KEYWORDS: &[&str] = &[
+    "class", "for", "fun", "if", "print", "return", "var", "while",
+];
+
+/// Like `keyword`, for a name that is a whole statement, as in `retrun;`.
+/// That is valid code, so only names one edit away count, and not one- or
+/// two-letter names, which are more likely variables than typos.
+pub fn statement_keyword(name: &str) -> Option<&'static str> {
+    if name.chars().count() < 3 {
+        return None;
+    }
+    STATEMENT_KEYWORDS
+        .iter()
+        .copied()
+        .filter(|keyword| distance(name, keyword) == 1)
+        .min()
+}
+
+/// The number of insertions, deletions, substitutions and transpositions
+/// of adjacent characters turning `a` into `b`, so that `retrun` is one
+/// edit away from `return`.
+fn distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+    // Three rows of the table: two back, the previous and the current.
+    let mut before = vec![0; b.len() + 1];
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    let mut current = vec![0; b.len() + 1];
+    for i in 1..=a.len() {
+        current[0] = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            current[j] = (previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(before[j - 2] + 1);
+            }
+        }
+        std::mem::swap(&mut before, &mut previous);
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+#[test]
+fn test_suggest() {
+    assert_eq!(distance("retrun", "return"), 1);
+    assert_eq!(distance("fucn", "fun"), 1);
+    assert_eq!(distance("kitten", "sitting"), 3);
+    assert_eq!(distance("", "abc"), 3);
+    assert_eq!(distance("héllo", "hello"), 1);
+
+    let names = ["count", "counter", "total", "fun", "for"];
+    assert_eq!(closest("coutn", names), Some("count"));
+    assert_eq!(closest("countr", names), Some("count"));
+    assert_eq!(closest("fucn", names), Some("fun"));
+    assert_eq!(closest("fo", names), Some("for"));
+    assert_eq!(closest("totally", names), Some("total"));
+    assert_eq!(closest("count", names), None);
+    assert_eq!(closest("tote", names), None);
+    assert_eq!(closest("x", names), None);
+
+    assert_eq!(keyword("retrun"), Some("return"));
+    assert_eq!(keyword("fucn"), Some("fun"));
+    assert_eq!(keyword("whiel"), Some("while"));
+    assert_eq!(keyword("count"), None);
+
+    assert_eq!(value_keyword("nill"), Some("nil"));
+    assert_eq!(value_keyword("ture"), Some("true"));
+    assert_eq!(value_keyword("retrun"), None);
+    assert_eq!(value_keyword("i"), None);
+}
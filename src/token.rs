@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::fmt::{self, Debug};
 
 use crate::literal::Object;
@@ -30,7 +31,15 @@ pub enum TokenType {
     // Literals.
     Identifier,
     String,
+    /// The part of an interpolated string up to the first `${`.
+    Interpolation,
+    /// The part of an interpolated string from a `}` to the next `${`.
+    InterpolationMiddle,
+    /// The part of an interpolated string from the last `}` to the end.
+    InterpolationEnd,
     Number,
+    /// A `///` or `/** */` comment; its literal is the documentation text.
+    DocComment,
 
     // Keywords.
     And,
@@ -49,6 +58,9 @@ pub enum TokenType {
     True,
     Var,
     While,
+
+    /// The text of a lexeme the scanner reported an error for.
+    Error,
     Eof,
 }
 
@@ -58,15 +70,73 @@ impl fmt::Display for TokenType {
     }
 }
 
+/// A half-open range of byte offsets into the source text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriviaKind {
+    /// A run of spaces, tabs and carriage returns.
+    Whitespace,
+    Newline,
+    LineComment,
+    BlockComment,
+}
+
+/// Source text between tokens that the grammar ignores.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trivia<'src> {
+    pub kind: TriviaKind,
+    pub text: Cow<'src, str>,
+    pub span: Span,
+}
+
+impl Trivia<'_> {
+    pub fn into_owned(self) -> Trivia<'static> {
+        Trivia {
+            kind: self.kind,
+            text: Cow::Owned(self.text.into_owned()),
+            span: self.span,
+        }
+    }
+}
+
+/// The trivia around one token. Whitespace and `//` comments after the
+/// token up to the end of its line trail it; everything else before the
+/// next token leads that one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TokenTrivia<'src> {
+    pub leading: Vec<Trivia<'src>>,
+    pub trailing: Vec<Trivia<'src>>,
+}
+
+/// A token whose lexeme is borrowed from the source text while scanning.
+/// Tokens kept in the AST are copied with `to_static`, because the tree
+/// outlives the source (closures survive across REPL lines).
 #[derive(Debug, Clone)]
-pub struct Token {
+pub struct Token<'src> {
     pub token_type: TokenType,
-    pub lexeme: String,
+    pub lexeme: Cow<'src, str>,
     pub literal: Option<Object>,
     pub line: usize,
+    /// 1-based column of the token's first character.
+    pub column: usize,
+    pub span: Span,
+    /// Only kept by `Scanner::lossless`, and boxed so that other tokens
+    /// stay small.
+    pub trivia: Option<Box<TokenTrivia<'src>>>,
 }
 
-impl Token {
+impl<'src> Token<'src> {
     pub fn new(
         token_type: TokenType,
         lexeme: String,
@@ -75,23 +145,79 @@ impl Token {
     ) -> Self {
         Token {
             token_type,
-            lexeme,
+            lexeme: Cow::Owned(lexeme),
             literal,
             line,
+            column: 0,
+            span: Span::default(),
+            trivia: None,
         }
     }
 
-    pub fn eof(line: usize) -> Token {
+    pub fn eof(line: usize) -> Token<'src> {
         Token {
             token_type: TokenType::Eof,
-            lexeme: "".to_string(),
+            lexeme: Cow::Borrowed(""),
             literal: None,
             line,
+            column: 0,
+            span: Span::default(),
+            trivia: None,
+        }
+    }
+}
+
+impl Token<'_> {
+    /// Detaches the token from the source text it was scanned from.
+    pub fn into_owned(self) -> Token<'static> {
+        Token {
+            token_type: self.token_type,
+            lexeme: Cow::Owned(self.lexeme.into_owned()),
+            literal: self.literal,
+            line: self.line,
+            column: self.column,
+            span: self.span,
+            trivia: self.trivia.map(|trivia| {
+                Box::new(TokenTrivia {
+                    leading: into_owned(trivia.leading),
+                    trailing: into_owned(trivia.trailing),
+                })
+            }),
+        }
+    }
+
+    /// Like `into_owned`, copying only what is borrowed from the source.
+    pub fn to_static(&self) -> Token<'static> {
+        Token {
+            token_type: self.token_type,
+            lexeme: Cow::Owned(self.lexeme.to_string()),
+            literal: self.literal.clone(),
+            line: self.line,
+            column: self.column,
+            span: self.span,
+            trivia: self.trivia.as_ref().map(|trivia| {
+                Box::new(TokenTrivia {
+                    leading: into_owned(trivia.leading.clone()),
+                    trailing: into_owned(trivia.trailing.clone()),
+                })
+            }),
         }
     }
+
+    pub fn leading_trivia(&self) -> &[Trivia<'_>] {
+        self.trivia.as_ref().map_or(&[], |trivia| &trivia.leading)
+    }
+
+    pub fn trailing_trivia(&self) -> &[Trivia<'_>] {
+        self.trivia.as_ref().map_or(&[], |trivia| &trivia.trailing)
+    }
+}
+
+fn into_owned(trivia: Vec<Trivia<'_>>) -> Vec<Trivia<'static>> {
+    trivia.into_iter().map(Trivia::into_owned).collect()
 }
 
-impl fmt::Display for Token {
+impl fmt::Display for Token<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{} {} {:?}", self.token_type, self.lexeme, self.literal)
     }
@@ -0,0 +1,389 @@
+//! Unicode support for identifiers: the UAX #31 character classes, NFC
+//! normalization, and the UTS #39 checks for identifiers that mix scripts
+//! or look like one another. The tables are generated by
+//! `tools/unicode_tables.pl`, so nothing is fetched at build time.
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+use crate::error::{Diagnostic, ErrorCode};
+use crate::token::{Token, TokenType};
+
+#[rustfmt::skip]
+mod tables;
+
+// Hangul syllables are composed and decomposed algorithmically.
+const S_BASE: u32 = 0xAC00;
+const L_BASE: u32 = 0x1100;
+const V_BASE: u32 = 0x1161;
+const T_BASE: u32 = 0x11A7;
+const L_COUNT: u32 = 19;
+const V_COUNT: u32 = 21;
+const T_COUNT: u32 = 28;
+const N_COUNT: u32 = V_COUNT * T_COUNT;
+const S_COUNT: u32 = L_COUNT * N_COUNT;
+
+/// Letters of other scripts that look like an ASCII letter, a hand-picked
+/// subset of the UTS #39 confusables covering Greek, Cyrillic and
+/// Armenian. Prototypes are the ASCII letter a reader would take them for.
+const CONFUSABLES: &[(char, char)] = &[
+    ('\u{391}', 'A'),
+    ('\u{392}', 'B'),
+    ('\u{395}', 'E'),
+    ('\u{396}', 'Z'),
+    ('\u{397}', 'H'),
+    ('\u{399}', 'I'),
+    ('\u{39A}', 'K'),
+    ('\u{39C}', 'M'),
+    ('\u{39D}', 'N'),
+    ('\u{39F}', 'O'),
+    ('\u{3A1}', 'P'),
+    ('\u{3A4}', 'T'),
+    ('\u{3A5}', 'Y'),
+    ('\u{3A7}', 'X'),
+    ('\u{3B1}', 'a'),
+    ('\u{3B3}', 'y'),
+    ('\u{3B9}', 'i'),
+    ('\u{3BD}', 'v'),
+    ('\u{3BF}', 'o'),
+    ('\u{3C1}', 'p'),
+    ('\u{3C5}', 'u'),
+    ('\u{405}', 'S'),
+    ('\u{406}', 'I'),
+    ('\u{408}', 'J'),
+    ('\u{410}', 'A'),
+    ('\u{412}', 'B'),
+    ('\u{415}', 'E'),
+    ('\u{41A}', 'K'),
+    ('\u{41C}', 'M'),
+    ('\u{41D}', 'H'),
+    ('\u{41E}', 'O'),
+    ('\u{420}', 'P'),
+    ('\u{421}', 'C'),
+    ('\u{422}', 'T'),
+    ('\u{423}', 'Y'),
+    ('\u{425}', 'X'),
+    ('\u{430}', 'a'),
+    ('\u{435}', 'e'),
+    ('\u{43E}', 'o'),
+    ('\u{440}', 'p'),
+    ('\u{441}', 'c'),
+    ('\u{443}', 'y'),
+    ('\u{445}', 'x'),
+    ('\u{455}', 's'),
+    ('\u{456}', 'i'),
+    ('\u{458}', 'j'),
+    ('\u{4AE}', 'Y'),
+    ('\u{4AF}', 'y'),
+    ('\u{4BB}', 'h'),
+    ('\u{4C0}', 'I'),
+    ('\u{4CF}', 'l'),
+    ('\u{501}', 'd'),
+    ('\u{51B}', 'q'),
+    ('\u{51D}', 'w'),
+    ('\u{570}', 'h'),
+    ('\u{578}', 'n'),
+    ('\u{57D}', 'u'),
+    ('\u{585}', 'o'),
+];
+
+/// Script combinations UTS #39 accepts as "highly restrictive": Latin
+/// with the scripts of one CJK writing system.
+const SCRIPT_SETS: &[&[&str]] = &[
+    &["Latin", "Han", "Hiragana", "Katakana"],
+    &["Latin", "Han", "Bopomofo"],
+    &["Latin", "Han", "Hangul"],
+];
+
+fn in_ranges(ranges: &[(char, char)], ch: char) -> bool {
+    ranges
+        .binary_search_by(|&(start, end)| {
+            if end < ch {
+                Ordering::Less
+            } else if start > ch {
+                Ordering::Greater
+            } else {
+                Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+
+pub fn is_xid_start(ch: char) -> bool {
+    in_ranges(tables::XID_START, ch)
+}
+
+pub fn is_xid_continue(ch: char) -> bool {
+    in_ranges(tables::XID_CONTINUE, ch)
+}
+
+fn combining_class(ch: char) -> u8 {
+    if ch.is_ascii() {
+        return 0;
+    }
+    let index = tables::COMBINING_CLASS.binary_search_by(|&(start, end, _)| {
+        if end < ch {
+            Ordering::Less
+        } else if start > ch {
+            Ordering::Greater
+        } else {
+            Ordering::Equal
+        }
+    });
+    index.map_or(0, |index| tables::COMBINING_CLASS[index].2)
+}
+
+fn script(ch: char) -> &'static str {
+    let index = tables::SCRIPT.binary_search_by(|&(start, end, _)| {
+        if end < ch {
+            Ordering::Less
+        } else if start > ch {
+            Ordering::Greater
+        } else {
+            Ordering::Equal
+        }
+    });
+    index.map_or("Unknown", |index| tables::SCRIPT[index].2)
+}
+
+fn decompose(ch: char, chars: &mut Vec<char>) {
+    let s_index = (ch as u32).wrapping_sub(S_BASE);
+    if s_index < S_COUNT {
+        let l = L_BASE + s_index / N_COUNT;
+        let v = V_BASE + (s_index % N_COUNT) / T_COUNT;
+        let t = T_BASE + s_index % T_COUNT;
+        chars.extend(char::from_u32(l));
+        chars.extend(char::from_u32(v));
+        if t != T_BASE {
+            chars.extend(char::from_u32(t));
+        }
+        return;
+    }
+    match tables::DECOMPOSITION.binary_search_by_key(&ch, |&(ch, _)| ch) {
+        Ok(index) => chars.extend_from_slice(tables::DECOMPOSITION[index].1),
+        Err(_) => chars.push(ch),
+    }
+}
+
+fn compose(first: char, second: char) -> Option<char> {
+    let (first, second) = (first as u32, second as u32);
+    let l_index = first.wrapping_sub(L_BASE);
+    let v_index = second.wrapping_sub(V_BASE);
+    if l_index < L_COUNT && v_index < V_COUNT {
+        return char::from_u32(S_BASE + (l_index * V_COUNT + v_index) * T_COUNT);
+    }
+    let s_index = first.wrapping_sub(S_BASE);
+    let t_index = second.wrapping_sub(T_BASE);
+    if s_index < S_COUNT && s_index % T_COUNT == 0 && t_index > 0 && t_index < T_COUNT {
+        return char::from_u32(first + t_index);
+    }
+    let pair = (char::from_u32(first)?, char::from_u32(second)?);
+    tables::COMPOSITION
+        .binary_search_by_key(&pair, |&(pair, _)| pair)
+        .ok()
+        .map(|index| tables::COMPOSITION[index].1)
+}
+
+/// Canonically decomposes `text` and puts combining marks in canonical
+/// order (Normalization Form D).
+fn nfd(text: &str) -> Vec<char> {
+    let mut chars = Vec::with_capacity(text.len());
+    for ch in text.chars() {
+        decompose(ch, &mut chars);
+    }
+
+    let mut i = 0;
+    while i < chars.len() {
+        let start = i;
+        while i < chars.len() && combining_class(chars[i]) != 0 {
+            i += 1;
+        }
+        // The sort is stable, so marks of equal class keep their order.
+        chars[start..i].sort_by_key(|&ch| combining_class(ch));
+        i += 1;
+    }
+    chars
+}
+
+/// Returns `text` in Normalization Form C, so that identifiers which
+/// render the same compare equal.
+pub fn nfc(text: &str) -> Cow<'_, str> {
+    if text.is_ascii() {
+        return Cow::Borrowed(text);
+    }
+
+    let mut composed: Vec<char> = Vec::with_capacity(text.len());
+    let mut starter = None;
+    for ch in nfd(text) {
+        let class = combining_class(ch);
+        if let Some(starter) = starter {
+            // A mark composes with the last starter unless something in
+            // between is a starter or has a class at least as high.
+            let blocked = composed.len() - 1 != starter
+                && combining_class(composed[composed.len() - 1]) >= class;
+            if !blocked {
+                if let Some(ch) = compose(composed[starter], ch) {
+                    composed[starter] = ch;
+                    continue;
+                }
+            }
+        }
+        if class == 0 {
+            starter = Some(composed.len());
+        }
+        composed.push(ch);
+    }
+
+    let composed = composed.into_iter().collect::<String>();
+    if composed == text {
+        Cow::Borrowed(text)
+    } else {
+        Cow::Owned(composed)
+    }
+}
+
+/// The UTS #39 skeleton of `name`: names that look alike share one.
+fn skeleton(name: &str) -> String {
+    let mut skeleton = String::with_capacity(name.len());
+    for ch in nfd(name) {
+        let prototype = CONFUSABLES
+            .binary_search_by_key(&ch, |&(ch, _)| ch)
+            .map(|index| CONFUSABLES[index].1)
+            .or_else(|_| {
+                tables::ASCII_VARIANTS
+                    .binary_search_by_key(&ch, |&(ch, _)| ch)
+                    .map(|index| tables::ASCII_VARIANTS[index].1)
+            });
+        skeleton.push(prototype.unwrap_or(ch));
+    }
+    skeleton
+}
+
+/// Returns the scripts `name` is written in when they don't make up a
+/// single writing system.
+fn mixed_scripts(name: &str) -> Option<Vec<&'static str>> {
+    let mut scripts = Vec::new();
+    for ch in name.chars() {
+        let script = script(ch);
+        if script != "Common" && script != "Inherited" && !scripts.contains(&script) {
+            scripts.push(script);
+        }
+    }
+    let single = scripts.len() <= 1
+        || SCRIPT_SETS
+            .iter()
+            .any(|set| scripts.iter().all(|script| set.contains(script)));
+    (!single).then_some(scripts)
+}
+
+/// The distinct identifiers of a token stream, each at its first
+/// occurrence, gathered while the stream goes by to the parser.
+#[derive(Default)]
+pub struct Identifiers<'src> {
+    seen: HashSet<Cow<'src, str>>,
+    first: Vec<Token<'src>>,
+}
+
+impl<'src> Identifiers<'src> {
+    pub fn add(&mut self, token: &Token<'src>) {
+        if token.token_type == TokenType::Identifier && self.seen.insert(token.lexeme.clone()) {
+            self.first.push(token.clone());
+        }
+    }
+
+    pub fn check(&self) -> Vec<Diagnostic> {
+        check_identifiers(&self.first)
+    }
+}
+
+/// Looks for identifiers that mix scripts, and for pairs of distinct
+/// identifiers that look the same. Like rustc, this only kicks in once
+/// some identifier isn't ASCII, so `l` and `I` alone are left alone.
+fn check_identifiers(tokens: &[Token]) -> Vec<Diagnostic> {
+    let identifiers = || {
+        tokens
+            .iter()
+            .filter(|token| token.token_type == TokenType::Identifier)
+    };
+    if identifiers().all(|token| token.lexeme.is_ascii()) {
+        return Vec::new();
+    }
+
+    let mut warnings = Vec::new();
+    let mut seen = HashSet::new();
+    let mut skeletons: HashMap<String, &Token> = HashMap::new();
+    for token in identifiers() {
+        let name = token.lexeme.as_ref();
+        if !seen.insert(name) {
+            continue;
+        }
+        if let Some(scripts) = mixed_scripts(name) {
+            warnings.push(Diagnostic::new(
+                ErrorCode::MixedScripts,
+                token.line,
+                token.column,
+                token.span,
+                format!(
+                    "Identifier '{}' mixes scripts ({}).",
+                    name,
+                    scripts.join(", ")
+                ),
+            ));
+        }
+        let first = *skeletons.entry(skeleton(name)).or_insert(token);
+        if first.lexeme != token.lexeme {
+            warnings.push(
+                Diagnostic::new(
+                    ErrorCode::ConfusableIdentifier,
+                    token.line,
+                    token.column,
+                    token.span,
+                    format!(
+                        "Identifier '{}' looks like '{}' from line {}.",
+                        name, first.lexeme, first.line
+                    ),
+                )
+                .with_label(first.span, format!("'{}' is declared here", first.lexeme)),
+            );
+        }
+    }
+    warnings
+}
+
+#[test]
+fn test_unicode_identifiers() {
+    assert!(is_xid_start('é') && is_xid_start('名') && is_xid_start('Ω'));
+    assert!(!is_xid_start('1') && !is_xid_start('\u{301}') && !is_xid_start('€'));
+    assert!(is_xid_continue('\u{301}') && is_xid_continue('٣'));
+
+    assert_eq!(nfc("e\u{301}"), "é");
+    assert_eq!(nfc("\u{212B}"), "\u{C5}");
+    assert_eq!(nfc("q\u{307}\u{323}"), "q\u{323}\u{307}");
+    assert_eq!(nfc("\u{1100}\u{1161}\u{11A8}"), "각");
+    assert_eq!(nfc("\u{1E0B}\u{323}"), "\u{1E0D}\u{307}");
+    assert!(matches!(nfc("déjà"), Cow::Borrowed(_)));
+
+    assert_eq!(mixed_scripts("pаy"), Some(vec!["Latin", "Cyrillic"]));
+    assert_eq!(mixed_scripts("größe_2"), None);
+    assert_eq!(mixed_scripts("漢字かなカナ_ascii"), None);
+    assert_eq!(skeleton("рау"), "pay");
+    assert_eq!(skeleton("ｐａｙ"), "pay");
+}
+
+#[test]
+fn test_unicode_check_identifiers() {
+    use crate::scanner::Scanner;
+
+    let (tokens, _) = Scanner::new("var I = 1; var l = I;").scan_tokens();
+    assert!(check_identifiers(&tokens).is_empty());
+
+    let (tokens, _) = Scanner::new("var pay = 1; var раy = pay + раy; var 名前 = 2;").scan_tokens();
+    let warnings = check_identifiers(&tokens)
+        .iter()
+        .map(|warning| format!("{:?}", warning))
+        .collect::<Vec<_>>();
+    assert_eq!(warnings.len(), 2);
+    assert!(warnings[0].contains("Identifier 'раy' mixes scripts (Cyrillic, Latin)."));
+    assert!(warnings[1].contains("Identifier 'раy' looks like 'pay' from line 1."));
+}
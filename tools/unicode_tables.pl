@@ -0,0 +1,130 @@
+#!/usr/bin/env perl
+#
+# Generates src/unicode/tables.rs from the Unicode Character Database that
+# ships with Perl, so the tables can be rebuilt without network access:
+#
+#     perl tools/unicode_tables.pl > src/unicode/tables.rs
+#
+# The Unicode version is whatever the local Perl was built with.
+
+use strict;
+use warnings;
+
+use Unicode::Normalize qw(NFC getCanon getCombinClass getCompat);
+use Unicode::UCD qw(charinfo prop_invlist prop_invmap);
+
+sub hex_char {
+    my ($cp) = @_;
+    return sprintf("'\\u{%X}'", $cp);
+}
+
+# Turns an inversion list into inclusive (start, end) ranges.
+sub ranges {
+    my @list = @_;
+    push @list, 0x110000 if @list % 2;
+    my @ranges;
+    for (my $i = 0; $i < @list; $i += 2) {
+        push @ranges, [$list[$i], $list[$i + 1] - 1];
+    }
+    return @ranges;
+}
+
+sub emit_ranges {
+    my ($name, $doc, @ranges) = @_;
+    print "/// $doc\n";
+    print "pub const $name: &[(char, char)] = &[\n";
+    print "    (", hex_char($_->[0]), ", ", hex_char($_->[1]), "),\n" for @ranges;
+    print "];\n\n";
+}
+
+my @xid_start = ranges(prop_invlist("XID_Start"));
+my @xid_continue = ranges(prop_invlist("XID_Continue"));
+my $is_hangul_syllable = sub { $_[0] >= 0xAC00 && $_[0] <= 0xD7A3 };
+
+print "// Generated by tools/unicode_tables.pl from Unicode ", Unicode::UCD::UnicodeVersion(), ".\n";
+print "// Do not edit by hand.\n\n";
+
+emit_ranges("XID_START", "Code points with the `XID_Start` property.", @xid_start);
+emit_ranges("XID_CONTINUE", "Code points with the `XID_Continue` property.", @xid_continue);
+
+# Canonical combining classes, as runs of equal non-zero classes.
+my @classes;
+for my $cp (0 .. 0x10FFFF) {
+    my $class = getCombinClass($cp);
+    next unless $class;
+    if (@classes && $classes[-1][1] == $cp - 1 && $classes[-1][2] == $class) {
+        $classes[-1][1] = $cp;
+    } else {
+        push @classes, [$cp, $cp, $class];
+    }
+}
+print "/// Runs of code points sharing a non-zero canonical combining class.\n";
+print "pub const COMBINING_CLASS: &[(char, char, u8)] = &[\n";
+printf "    (%s, %s, %d),\n", hex_char($_->[0]), hex_char($_->[1]), $_->[2] for @classes;
+print "];\n\n";
+
+# Full canonical decompositions and the primary composites.
+my (@decompositions, @compositions, @compatible);
+for my $cp (0 .. 0x10FFFF) {
+    next if $is_hangul_syllable->($cp);
+    my $canon = getCanon($cp);
+    if (defined $canon && $canon ne chr($cp)) {
+        push @decompositions, [$cp, [map { ord } split //, $canon]];
+        my @mapping = split / /, charinfo($cp)->{decomposition};
+        if (@mapping == 2 && NFC(chr($cp)) eq chr($cp)) {
+            push @compositions, [hex($mapping[0]), hex($mapping[1]), $cp];
+        }
+    }
+    my $compat = getCompat($cp);
+    if (defined $compat && length($compat) == 1 && $compat =~ /^[0-9A-Za-z]$/) {
+        my ($tag) = charinfo($cp)->{decomposition} =~ /^<(\w+)>/;
+        push @compatible, [$cp, ord($compat)] if defined $tag && ($tag eq "font" || $tag eq "wide");
+    }
+}
+print "/// Full canonical decompositions, Hangul syllables excepted.\n";
+print "pub const DECOMPOSITION: &[(char, &[char])] = &[\n";
+printf "    (%s, &[%s]),\n", hex_char($_->[0]), join(", ", map { hex_char($_) } @{ $_->[1] })
+    for @decompositions;
+print "];\n\n";
+
+@compositions = sort { $a->[0] <=> $b->[0] || $a->[1] <=> $b->[1] } @compositions;
+print "/// Pairs that canonically compose, Hangul syllables excepted.\n";
+print "pub const COMPOSITION: &[((char, char), char)] = &[\n";
+printf "    ((%s, %s), %s),\n", map { hex_char($_) } @$_ for @compositions;
+print "];\n\n";
+
+print "/// Font and width variants of ASCII letters and digits.\n";
+print "pub const ASCII_VARIANTS: &[(char, char)] = &[\n";
+printf "    (%s, '%s'),\n", hex_char($_->[0]), chr($_->[1]) for @compatible;
+print "];\n\n";
+
+# Scripts of identifier characters. Runs of one script are merged across
+# code points that can't appear in identifiers anyway.
+my ($script_list, $script_map) = prop_invmap("Script");
+my @scripts;
+my $script_at = sub {
+    my ($cp) = @_;
+    my ($lo, $hi) = (0, $#$script_list);
+    while ($lo < $hi) {
+        my $mid = int(($lo + $hi + 1) / 2);
+        if ($script_list->[$mid] <= $cp) { $lo = $mid } else { $hi = $mid - 1 }
+    }
+    return ($script_map->[$lo], $lo < $#$script_list ? $script_list->[$lo + 1] - 1 : 0x10FFFF);
+};
+for my $range (@xid_continue) {
+    my $cp = $range->[0];
+    while ($cp <= $range->[1]) {
+        my ($script, $end) = $script_at->($cp);
+        $end = $range->[1] if $end > $range->[1];
+        if (@scripts && $scripts[-1][2] eq $script) {
+            $scripts[-1][1] = $end;
+        } else {
+            push @scripts, [$cp, $end, $script];
+        }
+        $cp = $end + 1;
+    }
+}
+print "/// The script of every identifier character, as runs of one script.\n";
+print "pub const SCRIPT: &[(char, char, &str)] = &[\n";
+printf "    (%s, %s, \"%s\"),\n", hex_char($_->[0]), hex_char($_->[1]), $_->[2] for @scripts;
+print "];\n";